@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use pest::{
+    iterators::{Pair, Pairs},
+    prec_climber::{Assoc, Operator, PrecClimber},
+};
+
+use crate::{
+    calc::{
+        calc::{field_name, Rule},
+        value::broadcast,
+    },
+    fold_children, parse, parse_date_literal, parse_duration_literal, CompareOperator,
+    DivisionByZero, EvalOptions, ExpValue, Fold, Function, Identifier, ParseError, ValueError,
+    Visitor, DEFAULT_EPSILON,
+};
+
+lazy_static! {
+    static ref PREC_CLIMBER: PrecClimber<Rule> = PrecClimber::new(vec![
+        Operator::new(Rule::compare_eq, Assoc::Left)
+            | Operator::new(Rule::compare_ne, Assoc::Left)
+            | Operator::new(Rule::compare_lt, Assoc::Left)
+            | Operator::new(Rule::compare_le, Assoc::Left)
+            | Operator::new(Rule::compare_gt, Assoc::Left)
+            | Operator::new(Rule::compare_ge, Assoc::Left),
+        Operator::new(Rule::leftShift, Assoc::Left) | Operator::new(Rule::rightShift, Assoc::Left),
+        Operator::new(Rule::add, Assoc::Left) | Operator::new(Rule::subtract, Assoc::Left),
+        Operator::new(Rule::multiply, Assoc::Left)
+            | Operator::new(Rule::divide, Assoc::Left)
+            | Operator::new(Rule::int_divide, Assoc::Left),
+        Operator::new(Rule::modulus, Assoc::Left),
+        Operator::new(Rule::power, Assoc::Right),
+    ]);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn from(span: pest::Span) -> Span {
+        Span {
+            start: span.start(),
+            end: span.end(),
+        }
+    }
+
+    pub fn merge(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Modulus,
+    IntDivide,
+    LeftShift,
+    RightShift,
+    Compare(CompareOperator),
+}
+
+impl BinaryOperator {
+    pub fn from(rule: Rule) -> Option<BinaryOperator> {
+        match rule {
+            Rule::add => Some(BinaryOperator::Add),
+            Rule::subtract => Some(BinaryOperator::Subtract),
+            Rule::multiply => Some(BinaryOperator::Multiply),
+            Rule::divide => Some(BinaryOperator::Divide),
+            Rule::power => Some(BinaryOperator::Power),
+            Rule::modulus => Some(BinaryOperator::Modulus),
+            Rule::int_divide => Some(BinaryOperator::IntDivide),
+            Rule::leftShift => Some(BinaryOperator::LeftShift),
+            Rule::rightShift => Some(BinaryOperator::RightShift),
+            rule => CompareOperator::from(rule).map(BinaryOperator::Compare),
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Power => "^",
+            BinaryOperator::Modulus => "%",
+            BinaryOperator::IntDivide => "//",
+            BinaryOperator::LeftShift => "<<",
+            BinaryOperator::RightShift => ">>",
+            BinaryOperator::Compare(op) => op.symbol(),
+        }
+    }
+
+    pub fn apply(&self, lhs: ExpValue, rhs: ExpValue) -> ExpValue {
+        match self {
+            BinaryOperator::Add => lhs + rhs,
+            BinaryOperator::Subtract => lhs - rhs,
+            BinaryOperator::Multiply => lhs * rhs,
+            BinaryOperator::Divide => lhs / rhs,
+            BinaryOperator::Power => lhs.powf(rhs),
+            BinaryOperator::Modulus => lhs % rhs,
+            BinaryOperator::IntDivide => lhs.int_div(rhs),
+            BinaryOperator::LeftShift => lhs << rhs,
+            BinaryOperator::RightShift => lhs >> rhs,
+            BinaryOperator::Compare(op) => op.apply(&lhs, &rhs, DEFAULT_EPSILON),
+        }
+    }
+
+    pub fn apply_with(&self, lhs: ExpValue, rhs: ExpValue, options: &EvalOptions) -> ExpValue {
+        let (lhs, rhs) = match broadcast(lhs, rhs, |a, b| self.apply_with(a, b, options)) {
+            Ok(value) => return value,
+            Err(operands) => operands,
+        };
+        let (lhs, rhs) = (lhs.coerce(options.coercion), rhs.coerce(options.coercion));
+        let lhs = options.null_operand(lhs, &rhs);
+        let rhs = options.null_operand(rhs, &lhs);
+        let divides = matches!(
+            self,
+            BinaryOperator::Divide | BinaryOperator::IntDivide | BinaryOperator::Modulus
+        );
+        let dividend = lhs.as_number().is_some() || matches!(lhs, ExpValue::Duration(_));
+        if divides && dividend && rhs.is_zero() {
+            match &options.division_by_zero {
+                DivisionByZero::Ieee => {}
+                DivisionByZero::Error => return ExpValue::Error(ValueError::DivByZero),
+                DivisionByZero::Null => return ExpValue::Null,
+                DivisionByZero::Value(value) => return value.clone(),
+            }
+        }
+
+        if let BinaryOperator::Compare(op) = self {
+            return op.apply(&lhs, &rhs, options.epsilon);
+        }
+
+        #[cfg(feature = "decimal")]
+        if let Some(decimal) = &options.decimal {
+            let numeric = |v: &ExpValue| v.as_number().is_some();
+            let shift = matches!(self, BinaryOperator::LeftShift | BinaryOperator::RightShift);
+            if !shift && numeric(&lhs) && numeric(&rhs) {
+                return self
+                    .apply(lhs.to_decimal(), rhs.to_decimal())
+                    .round_decimal(decimal);
+            }
+        }
+        self.apply(lhs, rhs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AstKind {
+    Literal(ExpValue),
+    Identifier(String),
+    Function(Function),
+    Binary(BinaryOperator, Box<Ast>, Box<Ast>),
+    Array(Vec<Ast>),
+    /// `target[index]`.
+    Index(Box<Ast>, Box<Ast>),
+    /// `target.name`, or `target?.name` when the flag is set.
+    Member(Box<Ast>, String, bool),
+}
+
+struct SpanEraser;
+
+impl Fold for SpanEraser {
+    fn fold_ast(&mut self, ast: Ast) -> Ast {
+        let mut ast = fold_children(self, ast);
+        ast.span = Span::default();
+        if let AstKind::Function(func) = &mut ast.kind {
+            func.part_spans = vec![Span::default(); func.part_spans.len()];
+        }
+        ast
+    }
+}
+
+/// Identifiers a formula reads, in source order. A function argument like
+/// `subtask.points` depends on `subtask`.
+#[derive(Default)]
+pub(crate) struct Dependencies(pub(crate) Vec<Identifier>);
+
+impl Visitor for Dependencies {
+    fn visit_identifier(&mut self, name: &str, _span: Span) {
+        self.0.push(Identifier {
+            name: name.to_string(),
+        });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ast {
+    pub kind: AstKind,
+    pub span: Span,
+}
+
+impl Ast {
+    pub fn parse(input: &str) -> Result<Ast, ParseError> {
+        let pairs = parse(input)?;
+        Ok(Ast::from_pairs(pairs).unwrap_or(Ast {
+            kind: AstKind::Literal(ExpValue::Error(ValueError::Unsupported)),
+            span: Span::default(),
+        }))
+    }
+
+    pub fn from_pairs(expression: Pairs<Rule>) -> Option<Ast> {
+        PREC_CLIMBER.climb(expression, Ast::from, |lhs, op, rhs| {
+            let (lhs, rhs) = (lhs?, rhs?);
+            Some(Ast {
+                span: lhs.span.merge(rhs.span),
+                kind: AstKind::Binary(
+                    BinaryOperator::from(op.as_rule())?,
+                    Box::new(lhs),
+                    Box::new(rhs),
+                ),
+            })
+        })
+    }
+
+    pub fn from(expression: Pair<Rule>) -> Option<Ast> {
+        let span = Span::from(expression.as_span());
+        let kind = match expression.as_rule() {
+            Rule::expr => return Ast::from_pairs(expression.into_inner()),
+            Rule::num => {
+                let text = expression.as_str().trim();
+                AstKind::Literal(match text.parse::<i64>() {
+                    Ok(n) => ExpValue::Integer(n),
+                    Err(_) => ExpValue::Number(text.parse::<f64>().ok()?),
+                })
+            }
+            Rule::date => AstKind::Literal(
+                parse_date_literal(expression.as_str())
+                    .map(ExpValue::Date)
+                    .unwrap_or(ExpValue::Error(ValueError::InvalidLiteral)),
+            ),
+            Rule::duration => AstKind::Literal(
+                parse_duration_literal(expression.as_str())
+                    .map(ExpValue::Duration)
+                    .unwrap_or(ExpValue::Error(ValueError::InvalidLiteral)),
+            ),
+            Rule::ident | Rule::reference => AstKind::Identifier(field_name(expression)?),
+            Rule::array => AstKind::Array(
+                expression
+                    .into_inner()
+                    .map(Ast::from)
+                    .collect::<Option<Vec<Ast>>>()?,
+            ),
+            Rule::postfix => {
+                let mut inner = expression.into_inner();
+                let mut target = Ast::from(inner.next()?)?;
+                for accessor in inner {
+                    let span = target.span.merge(Span::from(accessor.as_span()));
+                    let optional = accessor.as_rule() == Rule::optional_member;
+                    let kind = match accessor.as_rule() {
+                        Rule::index => AstKind::Index(
+                            Box::new(target),
+                            Box::new(Ast::from(accessor.into_inner().next()?)?),
+                        ),
+                        _ => AstKind::Member(
+                            Box::new(target),
+                            field_name(accessor.into_inner().next()?)?,
+                            optional,
+                        ),
+                    };
+                    target = Ast { kind, span };
+                }
+                return Some(target);
+            }
+            Rule::function => AstKind::Function(Function::from(expression)?),
+            _ => return None,
+        };
+        Some(Ast { kind, span })
+    }
+
+    /// Source form of an identifier or a member chain on one, e.g. `parent.assignee?.name`.
+    pub fn path(&self) -> Option<String> {
+        match &self.kind {
+            AstKind::Identifier(name) => Some(name.clone()),
+            AstKind::Member(target, name, optional) => Some(format!(
+                "{}{}{}",
+                target.path()?,
+                if *optional { "?." } else { "." },
+                name
+            )),
+            _ => None,
+        }
+    }
+
+    /// Copy of the tree with every span zeroed, for comparing trees parsed from different text.
+    pub fn without_spans(&self) -> Ast {
+        self.clone().fold(&mut SpanEraser)
+    }
+
+    pub fn get_dependencies(&self) -> Vec<Identifier> {
+        let mut dependencies = Dependencies::default();
+        self.visit(&mut dependencies);
+        dependencies.0
+    }
+
+    pub fn eval(&self, table: &HashMap<String, ExpValue>) -> ExpValue {
+        self.eval_with(table, &EvalOptions::default())
+    }
+
+    pub fn eval_with(&self, table: &HashMap<String, ExpValue>, options: &EvalOptions) -> ExpValue {
+        let value = match &self.kind {
+            AstKind::Literal(value) => value.clone(),
+            AstKind::Identifier(name) => table
+                .get(name)
+                .cloned()
+                .unwrap_or(ExpValue::Error(ValueError::UnknownIdentifier)),
+            AstKind::Function(func) => func.eval_with(table, options),
+            AstKind::Binary(op, lhs, rhs) => op.apply_with(
+                lhs.eval_with(table, options),
+                rhs.eval_with(table, options),
+                options,
+            ),
+            AstKind::Array(items) => ExpValue::Array(
+                items
+                    .iter()
+                    .map(|item| item.eval_with(table, options))
+                    .collect(),
+            ),
+            AstKind::Index(target, index) => target
+                .eval_with(table, options)
+                .index(index.eval_with(table, options).coerce(options.coercion)),
+            AstKind::Member(target, name, optional) => {
+                target.eval_with(table, options).member(name, *optional)
+            }
+        };
+        options.check_nan(value)
+    }
+}
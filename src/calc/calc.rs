@@ -3,9 +3,7 @@ extern crate pest;
 use std::collections::HashMap;
 
 use pest::{
-    error::Error,
     iterators::{Pair, Pairs},
-    prec_climber::{Assoc, Operator, PrecClimber},
     Parser,
 };
 
@@ -13,16 +11,14 @@ use pest::{
 #[grammar = "calc/calc.pest"]
 pub struct Calculator;
 
-use Assoc::*;
-use Rule::*;
+use crate::{Ast, EvalOptions, ExpValue, ParseError, ValueError};
 
-use crate::ExpValue;
-
-pub fn parse(input: &str) -> Result<Pairs<Rule>, Error<Rule>> {
-    Calculator::parse(Rule::calculation, input)
+pub fn parse(input: &str) -> Result<Pairs<'_, Rule>, ParseError> {
+    Calculator::parse(Rule::calculation, input).map_err(|e| ParseError::from(e, input))
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Identifier {
     pub name: String,
 }
@@ -33,47 +29,30 @@ impl Identifier {
     }
 
     pub fn from(expression: Pair<Rule>) -> Option<Identifier> {
-        if let Rule::ident = expression.as_rule() {
-            return Some(Identifier {
-                name: expression.as_str().to_string(),
-            });
-        }
-        None
+        field_name(expression).map(|name| Identifier { name })
     }
 }
 
-pub fn eval(expression: Pairs<Rule>, table: &HashMap<String, ExpValue>) -> ExpValue {
-    let operators = PrecClimber::new(vec![
-        Operator::new(add, Left) | Operator::new(subtract, Left),
-        Operator::new(multiply, Left) | Operator::new(divide, Left),
-        Operator::new(modulus, Left),
-        Operator::new(power, Right),
-    ]);
-
-    operators.climb(
-        expression,
-        |pair| match pair.as_rule() {
-            Rule::num => ExpValue::Number(pair.as_str().trim().parse::<f64>().unwrap()),
-            Rule::expr => eval(pair.into_inner(), &table),
-            Rule::ident => {
-                let name = pair.as_str().trim();
-                let id = Identifier {
-                    name: name.to_string(),
-                };
+/// Name written by an `ident` or a `{...}` reference, without the braces.
+pub(crate) fn field_name(expression: Pair<Rule>) -> Option<String> {
+    match expression.as_rule() {
+        Rule::ident => Some(expression.as_str().trim().to_string()),
+        Rule::reference => Some(expression.into_inner().next()?.as_str().trim().to_string()),
+        _ => None,
+    }
+}
 
-                id.get_value(&table).unwrap_or(ExpValue::Error)
-            }
-            _ => ExpValue::Number(f64::NAN),
-        },
-        |lhs, op, rhs| match op.as_rule() {
-            Rule::add => lhs + rhs,
-            Rule::subtract => lhs - rhs,
-            Rule::multiply => lhs * rhs,
-            Rule::divide => lhs / rhs,
-            Rule::power => lhs.powf(rhs),
-            Rule::modulus => (lhs % rhs),
+pub fn eval(expression: Pairs<Rule>, table: &HashMap<String, ExpValue>) -> ExpValue {
+    eval_with(expression, table, &EvalOptions::default())
+}
 
-            _ => ExpValue::Error,
-        },
-    )
+pub fn eval_with(
+    expression: Pairs<Rule>,
+    table: &HashMap<String, ExpValue>,
+    options: &EvalOptions,
+) -> ExpValue {
+    match Ast::from_pairs(expression) {
+        Some(ast) => ast.eval_with(table, options),
+        None => ExpValue::Error(ValueError::Unsupported),
+    }
 }
@@ -0,0 +1,36 @@
+use crate::ExpValue;
+
+/// How operands of other types are turned into numbers before an operator is applied.
+///
+/// In `Strict` mode nothing is converted, so `"123" + 1` is `ValueError::TypeMismatch`.
+/// In `Lenient` mode numeric strings are parsed (`" 1.5 "` becomes `Number(1.5)`,
+/// `"123"` becomes `Integer(123)`) and booleans become `Integer(0)` or `Integer(1)`.
+/// Other strings and values are used as is in both modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Coercion {
+    #[default]
+    Strict,
+    Lenient,
+}
+
+impl ExpValue {
+    /// Applies the [`Coercion`] rules to a single operand.
+    pub fn coerce(self, coercion: Coercion) -> ExpValue {
+        match (coercion, self) {
+            (Coercion::Strict, value) => value,
+            (Coercion::Lenient, ExpValue::Bool(b)) => ExpValue::Integer(b as i64),
+            (Coercion::Lenient, ExpValue::String(s)) => {
+                let text = s.trim();
+                if let Ok(n) = text.parse::<i64>() {
+                    ExpValue::Integer(n)
+                } else {
+                    match text.parse::<f64>() {
+                        Ok(n) if n.is_finite() => ExpValue::Number(n),
+                        _ => ExpValue::String(s),
+                    }
+                }
+            }
+            (_, value) => value,
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::str::FromStr;
+
+use rust_decimal::{prelude::ToPrimitive, RoundingStrategy};
+
+pub use rust_decimal::Decimal;
+
+use crate::{ExpValue, RoundingMode, ValueError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecimalOptions {
+    /// Digits kept after the decimal point after every operation, at most 28.
+    pub precision: u32,
+    pub rounding: RoundingMode,
+}
+
+impl Default for DecimalOptions {
+    fn default() -> Self {
+        DecimalOptions {
+            precision: 10,
+            rounding: RoundingMode::HalfUp,
+        }
+    }
+}
+
+impl RoundingMode {
+    pub fn strategy(&self) -> RoundingStrategy {
+        match self {
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            RoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+            RoundingMode::Down => RoundingStrategy::ToZero,
+            RoundingMode::Up => RoundingStrategy::AwayFromZero,
+            RoundingMode::Floor => RoundingStrategy::ToNegativeInfinity,
+            RoundingMode::Ceiling => RoundingStrategy::ToPositiveInfinity,
+        }
+    }
+}
+
+/// Converts through the shortest round-trip text, so `0.1f64` becomes exactly `0.1`.
+pub fn decimal_from_f64(value: f64) -> Option<Decimal> {
+    if !value.is_finite() {
+        return None;
+    }
+    Decimal::from_str(&value.to_string())
+        .or_else(|_| Decimal::from_scientific(&format!("{:e}", value)))
+        .ok()
+}
+
+/// Both operands as decimals when at least one of them is a decimal and the other is numeric.
+pub(crate) fn decimal_operands(lhs: &ExpValue, rhs: &ExpValue) -> Option<(Decimal, Decimal)> {
+    match (lhs, rhs) {
+        (ExpValue::Decimal(a), ExpValue::Decimal(b)) => Some((*a, *b)),
+        (ExpValue::Decimal(a), ExpValue::Number(b)) => Some((*a, decimal_from_f64(*b)?)),
+        (ExpValue::Number(a), ExpValue::Decimal(b)) => Some((decimal_from_f64(*a)?, *b)),
+        (ExpValue::Decimal(a), ExpValue::Integer(b)) => Some((*a, Decimal::from(*b))),
+        (ExpValue::Integer(a), ExpValue::Decimal(b)) => Some((Decimal::from(*a), *b)),
+        _ => None,
+    }
+}
+
+pub(crate) fn decimal_result(value: Option<Decimal>) -> ExpValue {
+    value
+        .map(ExpValue::Decimal)
+        .unwrap_or(ExpValue::Error(ValueError::Overflow))
+}
+
+fn checked_powi(mut base: Decimal, mut exponent: u64) -> Option<Decimal> {
+    let mut result = Decimal::ONE;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.checked_mul(base)?;
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            base = base.checked_mul(base)?;
+        }
+    }
+    Some(result)
+}
+
+pub(crate) fn decimal_pow(base: Decimal, exponent: Decimal) -> ExpValue {
+    if exponent.fract().is_zero() {
+        if let Some(exponent) = exponent.to_i64() {
+            let power = checked_powi(base, exponent.unsigned_abs());
+            return decimal_result(if exponent >= 0 {
+                power
+            } else {
+                power.and_then(|p| Decimal::ONE.checked_div(p))
+            });
+        }
+    }
+    match (base.to_f64(), exponent.to_f64()) {
+        (Some(base), Some(exponent)) => match base.powf(exponent) {
+            power if power.is_nan() => ExpValue::Error(ValueError::NaN),
+            power => decimal_result(decimal_from_f64(power)),
+        },
+        _ => ExpValue::Error(ValueError::Overflow),
+    }
+}
+
+impl ExpValue {
+    /// Turns a `Number` or `Integer` into a `Decimal`, other values are returned unchanged.
+    pub fn to_decimal(self) -> ExpValue {
+        match self {
+            ExpValue::Number(n) => decimal_result(decimal_from_f64(n)),
+            ExpValue::Integer(n) => ExpValue::Decimal(Decimal::from(n)),
+            other => other,
+        }
+    }
+
+    pub fn round_decimal(self, options: &DecimalOptions) -> ExpValue {
+        match self {
+            ExpValue::Decimal(d) => ExpValue::Decimal(
+                d.round_dp_with_strategy(options.precision, options.rounding.strategy())
+                    .normalize(),
+            ),
+            other => other,
+        }
+    }
+}
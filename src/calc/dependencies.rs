@@ -0,0 +1,154 @@
+use crate::{
+    walk_ast, walk_function, walk_function_part, Ast, AstKind, Expression, ExpressionPart,
+    Function, FunctionPart, Span, Visitor,
+};
+
+/// How a formula uses a field: its value is computed with, or items are selected by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum DependencyRole {
+    Value,
+    Filter,
+}
+
+/// A field a formula reads, `root` is a field of the record and `path` the sub-fields read
+/// on it. `SUM(subtask.estimatePoint; status=2)` reads `subtask.estimatePoint` as a value
+/// and filters on `subtask.status`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dependency {
+    pub root: String,
+    pub path: Vec<String>,
+    pub role: DependencyRole,
+}
+
+impl Dependency {
+    fn from(root: &str, path: &[String], role: DependencyRole) -> Dependency {
+        Dependency {
+            root: root.to_string(),
+            path: path.to_vec(),
+            role,
+        }
+    }
+}
+
+/// Root and member names of `a.b?.c`, `None` when the chain does not start at a field.
+fn member_chain(ast: &Ast) -> Option<(&str, Vec<String>)> {
+    match &ast.kind {
+        AstKind::Identifier(name) => Some((name, vec![])),
+        AstKind::Member(target, name, _) => {
+            let (root, mut path) = member_chain(target)?;
+            path.push(name.clone());
+            Some((root, path))
+        }
+        _ => None,
+    }
+}
+
+/// Dependencies in order of first appearance, each once. A field argument is visited as its
+/// root and then its members, which are collected in `argument` until the argument ends.
+#[derive(Default)]
+struct FieldDependencies {
+    dependencies: Vec<Dependency>,
+    argument: Option<Dependency>,
+    collection: Option<String>,
+}
+
+impl FieldDependencies {
+    fn push(&mut self, dependency: Dependency) {
+        if !self.dependencies.contains(&dependency) {
+            self.dependencies.push(dependency);
+        }
+    }
+}
+
+impl Visitor for FieldDependencies {
+    fn visit_ast(&mut self, ast: &Ast) {
+        match member_chain(ast) {
+            Some((root, path)) => self.push(Dependency {
+                root: root.to_string(),
+                path,
+                role: DependencyRole::Value,
+            }),
+            None => walk_ast(self, ast),
+        }
+    }
+
+    fn visit_identifier(&mut self, name: &str, _span: Span) {
+        self.argument = Some(Dependency::from(name, &[], DependencyRole::Value));
+    }
+
+    fn visit_member(&mut self, name: &str, _span: Span) {
+        if let Some(argument) = &mut self.argument {
+            argument.path.push(name.to_string());
+        }
+    }
+
+    fn visit_filter_field(&mut self, path: &[String], _span: Span) {
+        let (root, path) = match (&self.collection, path.split_first()) {
+            (Some(root), _) => (root.as_str(), path),
+            (None, Some((root, path))) => (root.as_str(), path),
+            (None, None) => return,
+        };
+        self.push(Dependency::from(root, path, DependencyRole::Filter))
+    }
+
+    fn visit_function(&mut self, func: &Function, _span: Span) {
+        // filters select items of the collection named by the first argument
+        let collection = match func.parts.first() {
+            Some(FunctionPart::Identifier(root) | FunctionPart::IdentifierFWithField(root, _)) => {
+                Some(root.clone())
+            }
+            _ => None,
+        };
+        let outer = std::mem::replace(&mut self.collection, collection);
+        walk_function(self, func);
+        self.collection = outer;
+    }
+
+    fn visit_function_part(&mut self, part: &FunctionPart, span: Span) {
+        walk_function_part(self, part, span);
+        if let Some(argument) = self.argument.take() {
+            self.push(argument);
+        }
+    }
+}
+
+impl Ast {
+    /// Fields this formula reads with their sub-field paths, in order of first appearance
+    /// and without duplicates.
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        let mut dependencies = FieldDependencies::default();
+        self.visit(&mut dependencies);
+        dependencies.dependencies
+    }
+}
+
+impl Function {
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        let mut dependencies = FieldDependencies::default();
+        dependencies.visit_function(self, Span::default());
+        dependencies.dependencies
+    }
+}
+
+impl Expression {
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        let mut dependencies = FieldDependencies::default();
+        for part in &self.parts {
+            match part {
+                ExpressionPart::Identifier(identifier) => dependencies.push(Dependency::from(
+                    &identifier.name,
+                    &[],
+                    DependencyRole::Value,
+                )),
+                ExpressionPart::Function(func) => {
+                    dependencies.visit_function(func, Span::default())
+                }
+                ExpressionPart::Operator(_) => {}
+            }
+        }
+        dependencies.dependencies
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+
+use crate::{
+    calc::field_format::{fixed, format_date, format_duration},
+    format_date_literal, format_duration_literal, ExpValue, FormatOptions, Locale, ValueError,
+    MILLIS_PER_DAY, MILLIS_PER_MINUTE,
+};
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:?}", self)
+    }
+}
+
+/// Canonical text: `3`, `0.5`, `true`, `null`, `2024-03-01`, `3d4h`, `[1, "a"]` and
+/// `{name: "Ann"}`. Strings are written as is at the top level and quoted when nested.
+impl fmt::Display for ExpValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpValue::Error(e) => write!(f, "{}", e),
+            ExpValue::Null => write!(f, "null"),
+            ExpValue::Bool(b) => write!(f, "{}", b),
+            ExpValue::Number(n) => write!(f, "{}", n),
+            ExpValue::Integer(n) => write!(f, "{}", n),
+            #[cfg(feature = "decimal")]
+            ExpValue::Decimal(d) => write!(f, "{}", d.normalize()),
+            ExpValue::String(s) => write!(f, "{}", s),
+            ExpValue::Date(millis) if millis.rem_euclid(MILLIS_PER_DAY) == 0 => {
+                write!(f, "{}", &format_date_literal(*millis)[..10])
+            }
+            ExpValue::Date(millis) => write!(f, "{}", format_date_literal(*millis)),
+            ExpValue::Duration(millis) => write!(f, "{}", format_duration_literal(*millis)),
+            ExpValue::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.fmt_nested(f)?;
+                }
+                write!(f, "]")
+            }
+            ExpValue::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", name)?;
+                    value.fmt_nested(f)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Inserts `,` between groups of three integer digits, `-1234567.5` becomes `-1,234,567.5`.
+fn group_thousands(text: &str) -> String {
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text),
+    };
+    let (integer, fraction) = match digits.find('.') {
+        Some(i) => digits.split_at(i),
+        None => (digits, ""),
+    };
+    if !integer.chars().all(|c| c.is_ascii_digit()) {
+        return text.to_string();
+    }
+    let mut grouped = String::new();
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    format!("{}{}{}", sign, grouped, fraction)
+}
+
+impl ExpValue {
+    fn fmt_nested(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpValue::String(s) => write!(f, "{:?}", s),
+            value => write!(f, "{}", value),
+        }
+    }
+
+    /// Text for UI output: grouped digits, `options.precision` digits after the point when
+    /// set, dates in the local format and time zone, localized booleans and durations,
+    /// and an empty string for null.
+    pub fn to_locale_string(&self, options: &FormatOptions) -> String {
+        let zh = options.locale == Locale::ZhCn;
+        match self {
+            ExpValue::Null => String::new(),
+            ExpValue::Bool(b) => match (zh, b) {
+                (false, true) => "Yes".to_string(),
+                (false, false) => "No".to_string(),
+                (true, true) => "是".to_string(),
+                (true, false) => "否".to_string(),
+            },
+            ExpValue::Number(n) if n.is_finite() => group_thousands(&match options.precision {
+                Some(precision) => fixed(options.rounding.round(*n, precision), precision),
+                None => n.to_string(),
+            }),
+            ExpValue::Integer(n) => group_thousands(&match options.precision {
+                Some(precision) if precision > 0 => {
+                    format!("{}.{}", n, "0".repeat(precision as usize))
+                }
+                _ => n.to_string(),
+            }),
+            #[cfg(feature = "decimal")]
+            ExpValue::Decimal(d) => group_thousands(&match options.precision {
+                Some(precision) => format!(
+                    "{:.*}",
+                    precision as usize,
+                    d.round_dp_with_strategy(precision, options.rounding.strategy())
+                ),
+                None => d.normalize().to_string(),
+            }),
+            ExpValue::Date(millis) => {
+                match millis.checked_add(options.utc_offset_minutes as i64 * MILLIS_PER_MINUTE) {
+                    Some(local) => {
+                        format_date(local, local.rem_euclid(MILLIS_PER_DAY) != 0, options.locale)
+                    }
+                    None => ExpValue::Error(ValueError::Overflow).to_string(),
+                }
+            }
+            ExpValue::Duration(millis) => format_duration(*millis, true, options.locale),
+            ExpValue::Array(items) => items
+                .iter()
+                .map(|item| item.to_locale_string(options))
+                .collect::<Vec<_>>()
+                .join(if zh { "、" } else { ", " }),
+            value => value.to_string(),
+        }
+    }
+}
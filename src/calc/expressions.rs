@@ -3,11 +3,13 @@ use pest::iterators::{Pair, Pairs};
 use crate::{calc::calc::Rule, Function, Identifier};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Expression {
     pub parts: Vec<ExpressionPart>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExpressionPart {
     Identifier(Identifier),
     Function(Function),
@@ -53,9 +55,7 @@ impl Expression {
                     return Some(Expression {
                         parts: expr
                             .into_inner()
-                            .map(ExpressionPart::from)
-                            .filter(|p| p.is_some())
-                            .map(|p| p.unwrap())
+                            .filter_map(ExpressionPart::from)
                             .collect::<Vec<ExpressionPart>>(),
                     });
                 }
@@ -80,21 +80,16 @@ impl Expression {
 impl ExpressionPart {
     pub fn from(expression: Pair<Rule>) -> Option<ExpressionPart> {
         match expression.as_rule() {
-            Rule::function => {
-                return match Function::from(expression) {
-                    Some(i) => Some(ExpressionPart::Function(i)),
-                    None => None,
-                };
-            }
-            Rule::ident => {
-                return match Identifier::from(expression) {
-                    Some(i) => Some(ExpressionPart::Identifier(i)),
-                    None => None,
-                };
-            }
-            Rule::operation => {
-                return Some(ExpressionPart::Operator(expression.as_str().to_string()));
+            Rule::function => Function::from(expression).map(ExpressionPart::Function),
+            Rule::ident | Rule::reference => {
+                Identifier::from(expression).map(ExpressionPart::Identifier)
             }
+            Rule::postfix => expression
+                .into_inner()
+                .next()
+                .and_then(Identifier::from)
+                .map(ExpressionPart::Identifier),
+            Rule::operation => Some(ExpressionPart::Operator(expression.as_str().to_string())),
             _ => None,
         }
     }
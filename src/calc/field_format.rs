@@ -0,0 +1,308 @@
+use crate::{
+    civil_from_days, ExpValue, ValueError, MILLIS_PER_DAY, MILLIS_PER_HOUR, MILLIS_PER_MINUTE,
+    MILLIS_PER_SECOND,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Integer,
+    Decimal,
+    PercentageNumber,
+    PercentageBar,
+    Date,
+    DateTime,
+}
+
+impl FieldType {
+    pub fn from_name(name: &str) -> Option<FieldType> {
+        match name.trim().to_lowercase().as_str() {
+            "integer" => Some(FieldType::Integer),
+            "decimal" => Some(FieldType::Decimal),
+            "percentage_number" => Some(FieldType::PercentageNumber),
+            "percentage_bar" => Some(FieldType::PercentageBar),
+            "date" => Some(FieldType::Date),
+            "datetime" => Some(FieldType::DateTime),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FieldType::Integer => "integer",
+            FieldType::Decimal => "decimal",
+            FieldType::PercentageNumber => "percentage_number",
+            FieldType::PercentageBar => "percentage_bar",
+            FieldType::Date => "date",
+            FieldType::DateTime => "datetime",
+        }
+    }
+
+    fn default_precision(&self) -> u32 {
+        match self {
+            FieldType::Integer | FieldType::Date | FieldType::DateTime => 0,
+            _ => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    ZhCn,
+}
+
+impl Locale {
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        match tag.trim().to_lowercase().replace('_', "-").as_str() {
+            "en" | "en-us" | "en-gb" => Some(Locale::En),
+            "zh" | "zh-cn" | "zh-hans" => Some(Locale::ZhCn),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    /// Ties away from zero, `2.5 -> 3`, `-2.5 -> -3`.
+    #[default]
+    HalfUp,
+    /// Ties to even, `2.5 -> 2`, `3.5 -> 4`.
+    HalfEven,
+    /// Towards zero.
+    Down,
+    /// Away from zero.
+    Up,
+    Floor,
+    Ceiling,
+}
+
+impl RoundingMode {
+    /// `value` rounded to `precision` digits after the point. The shortest decimal that reads
+    /// back as `value` is rounded, so `1.005` rounds like the decimal it was written as and
+    /// `2.9999996` stays below 3. Values with no digits at that scale are returned unchanged.
+    pub fn round(&self, value: f64, precision: u32) -> f64 {
+        if !value.is_finite() {
+            return value;
+        }
+        // `d.ddde-x`, the digits without trailing zeros and the exponent of the first one
+        let text = format!("{:e}", value.abs());
+        let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+        let digits = mantissa.replace('.', "").into_bytes();
+        let exponent = exponent.parse::<i64>().unwrap_or(0);
+        let keep = exponent + 1 + precision as i64;
+        if keep >= digits.len() as i64 {
+            return value;
+        }
+        let (kept, dropped) = digits.split_at(keep.max(0) as usize);
+        // digit after the last kept one, `0` when the value is below the first dropped place
+        let next = match keep < 0 {
+            true => b'0',
+            false => dropped[0],
+        };
+        let tie = keep >= 0 && next == b'5' && dropped.len() == 1;
+        let negative = value < 0.0;
+        let up = match self {
+            RoundingMode::HalfUp => next >= b'5',
+            RoundingMode::HalfEven => {
+                let odd = kept.last().is_some_and(|digit| digit % 2 == 1);
+                next > b'5' || (next == b'5' && !tie) || (tie && odd)
+            }
+            RoundingMode::Down => false,
+            RoundingMode::Up => true,
+            RoundingMode::Floor => negative,
+            RoundingMode::Ceiling => !negative,
+        };
+        let kept = std::str::from_utf8(kept)
+            .ok()
+            .and_then(|kept| kept.parse::<u64>().ok())
+            .unwrap_or(0);
+        let rounded = format!("{}e-{}", kept + up as u64, precision)
+            .parse::<f64>()
+            .unwrap_or(value);
+        match negative {
+            true => -rounded,
+            false => rounded,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FormatOptions {
+    pub locale: Locale,
+    /// Digits after the decimal point, defaults to the field type's own precision.
+    pub precision: Option<u32>,
+    pub rounding: RoundingMode,
+    /// Offset applied to dates before rendering, in minutes east of UTC.
+    pub utc_offset_minutes: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormattedValue {
+    pub display: String,
+    pub value: ExpValue,
+}
+
+fn as_number(value: &ExpValue) -> Option<f64> {
+    value.as_number().filter(|n| n.is_finite())
+}
+
+pub(crate) fn fixed(value: f64, precision: u32) -> String {
+    let text = format!("{:.*}", precision as usize, value);
+    if text
+        .trim_start_matches('-')
+        .chars()
+        .all(|c| c == '0' || c == '.')
+    {
+        text.trim_start_matches('-').to_string()
+    } else {
+        text
+    }
+}
+
+pub(crate) fn format_date(millis: i64, with_time: bool, locale: Locale) -> String {
+    let days = millis.div_euclid(MILLIS_PER_DAY);
+    let in_day = millis.rem_euclid(MILLIS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    let hour = in_day / MILLIS_PER_HOUR;
+    let minute = in_day % MILLIS_PER_HOUR / MILLIS_PER_MINUTE;
+    match (locale, with_time) {
+        (Locale::En, false) => format!("{:04}-{:02}-{:02}", year, month, day),
+        (Locale::En, true) => format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            year, month, day, hour, minute
+        ),
+        (Locale::ZhCn, false) => format!("{}年{}月{}日", year, month, day),
+        (Locale::ZhCn, true) => format!("{}年{}月{}日 {:02}:{:02}", year, month, day, hour, minute),
+    }
+}
+
+pub(crate) fn format_duration(millis: i64, with_time: bool, locale: Locale) -> String {
+    let sign = if millis < 0 { "-" } else { "" };
+    let millis = millis.unsigned_abs() as i64;
+    let units: &[(i64, &str, &str)] = if with_time {
+        &[
+            (MILLIS_PER_DAY, "d", "天"),
+            (MILLIS_PER_HOUR, "h", "小时"),
+            (MILLIS_PER_MINUTE, "m", "分钟"),
+        ]
+    } else {
+        &[(MILLIS_PER_DAY, "d", "天")]
+    };
+
+    let mut rest = millis;
+    let mut parts = vec![];
+    for (size, en, zh) in units {
+        let amount = rest / size;
+        rest %= size;
+        if amount > 0 {
+            parts.push(match locale {
+                Locale::En => format!("{}{}", amount, en),
+                Locale::ZhCn => format!("{}{}", amount, zh),
+            });
+        }
+    }
+    if parts.is_empty() {
+        let (_, en, zh) = units[units.len() - 1];
+        parts.push(match locale {
+            Locale::En => format!("0{}", en),
+            Locale::ZhCn => format!("0{}", zh),
+        });
+    }
+    let separator = match locale {
+        Locale::En => " ",
+        Locale::ZhCn => "",
+    };
+    format!("{}{}", sign, parts.join(separator))
+}
+
+pub fn format_value(
+    value: &ExpValue,
+    field_type: FieldType,
+    options: &FormatOptions,
+) -> Option<FormattedValue> {
+    let precision = options
+        .precision
+        .unwrap_or_else(|| field_type.default_precision());
+    let rounding = options.rounding;
+
+    match field_type {
+        FieldType::Integer | FieldType::Decimal => {
+            let precision = if field_type == FieldType::Integer {
+                0
+            } else {
+                precision
+            };
+            #[cfg(feature = "decimal")]
+            if let ExpValue::Decimal(d) = value {
+                let rounded = d.round_dp_with_strategy(precision, rounding.strategy());
+                return Some(FormattedValue {
+                    display: format!("{:.*}", precision as usize, rounded),
+                    value: ExpValue::Decimal(rounded),
+                });
+            }
+            let rounded = rounding.round(as_number(value)?, precision);
+            Some(FormattedValue {
+                display: fixed(rounded, precision),
+                value: if field_type == FieldType::Integer && rounded.abs() < i64::MAX as f64 {
+                    ExpValue::Integer(rounded as i64)
+                } else {
+                    ExpValue::Number(rounded)
+                },
+            })
+        }
+        FieldType::PercentageNumber | FieldType::PercentageBar => {
+            let mut ratio = rounding.round(as_number(value)?, precision.saturating_add(2));
+            if field_type == FieldType::PercentageBar {
+                ratio = ratio.clamp(0.0, 1.0);
+            }
+            Some(FormattedValue {
+                display: format!("{}%", fixed(ratio * 100.0, precision)),
+                value: ExpValue::Number(ratio),
+            })
+        }
+        FieldType::Date | FieldType::DateTime => {
+            let with_time = field_type == FieldType::DateTime;
+            match value {
+                ExpValue::Date(millis) => {
+                    let offset = options.utc_offset_minutes as i64 * MILLIS_PER_MINUTE;
+                    let local = millis.checked_add(offset);
+                    let stored = match (with_time, local) {
+                        (true, _) => Some(millis - millis.rem_euclid(MILLIS_PER_SECOND)),
+                        (false, Some(local)) => {
+                            (local - local.rem_euclid(MILLIS_PER_DAY)).checked_sub(offset)
+                        }
+                        (false, None) => None,
+                    };
+                    let (local, stored) = match (local, stored) {
+                        (Some(local), Some(stored)) => (local, stored),
+                        _ => {
+                            let overflow = ExpValue::Error(ValueError::Overflow);
+                            return Some(FormattedValue {
+                                display: overflow.to_string(),
+                                value: overflow,
+                            });
+                        }
+                    };
+                    Some(FormattedValue {
+                        display: format_date(local, with_time, options.locale),
+                        value: ExpValue::Date(stored),
+                    })
+                }
+                ExpValue::Duration(millis) => {
+                    let unit = if with_time {
+                        MILLIS_PER_MINUTE
+                    } else {
+                        MILLIS_PER_DAY
+                    };
+                    let stored = millis - millis % unit;
+                    Some(FormattedValue {
+                        display: format_duration(stored, with_time, options.locale),
+                        value: ExpValue::Duration(stored),
+                    })
+                }
+                _ => None,
+            }
+        }
+    }
+}
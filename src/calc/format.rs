@@ -0,0 +1,213 @@
+use pest::Parser;
+
+use crate::{
+    calc::calc::{Calculator, Rule},
+    format_date_literal, format_duration_literal, Ast, AstKind, BinaryOperator, ExpValue, Function,
+    FunctionPart, ParseError, MILLIS_PER_DAY,
+};
+
+/// Normalizes a formula: canonical operator symbols, single spaces around binary
+/// operators, `; ` between arguments, upper case function names and only the
+/// parentheses the precedence requires. Parsing the result gives the same AST up to
+/// spans and the case of function names.
+pub fn format(input: &str) -> Result<String, ParseError> {
+    let ast = Ast::parse(input)?;
+    let mut out = String::new();
+    ast.write_source(Some(input), &mut out);
+    Ok(out)
+}
+
+impl BinaryOperator {
+    /// Binding strength, higher binds tighter.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Compare(_) => 0,
+            BinaryOperator::LeftShift | BinaryOperator::RightShift => 1,
+            BinaryOperator::Add | BinaryOperator::Subtract => 2,
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::IntDivide => 3,
+            BinaryOperator::Modulus => 4,
+            BinaryOperator::Power => 5,
+        }
+    }
+
+    pub fn is_right_associative(&self) -> bool {
+        matches!(self, BinaryOperator::Power)
+    }
+}
+
+fn literal_source(value: &ExpValue) -> String {
+    match value {
+        ExpValue::Number(n) if n.is_infinite() => match n.is_sign_positive() {
+            true => "1e999".to_string(),
+            false => "-1e999".to_string(),
+        },
+        ExpValue::Number(n) => format!("{:?}", n),
+        ExpValue::Date(millis) if millis.rem_euclid(MILLIS_PER_DAY) == 0 => {
+            format!("#{}#", &format_date_literal(*millis)[..10])
+        }
+        ExpValue::Date(millis) => format!("#{}#", format_date_literal(*millis)),
+        ExpValue::Duration(millis) => format_duration_literal(*millis),
+        ExpValue::Array(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(literal_source)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        value => value.to_string(),
+    }
+}
+
+/// `name` as is when it reads as an identifier, otherwise as a `{name}` reference.
+pub(crate) fn field_source(name: &str) -> String {
+    match Calculator::parse(Rule::ident, name) {
+        Ok(pairs) if pairs.as_str() == name => name.to_string(),
+        _ => format!("{{{}}}", name),
+    }
+}
+
+/// `a.{b c}` for the names `["a", "b c"]`.
+pub(crate) fn path_source<S: AsRef<str>>(path: &[S]) -> String {
+    path.iter()
+        .map(|name| field_source(name.as_ref()))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+impl FunctionPart {
+    fn write_source(&self, source: Option<&str>, out: &mut String) {
+        match self {
+            FunctionPart::Identifier(name) => out.push_str(&field_source(name)),
+            FunctionPart::IdentifierFWithField(root, path) => {
+                out.push_str(&format!("{}.{}", field_source(root), path_source(path)))
+            }
+            FunctionPart::CompareExpression(field, op, value) => {
+                let value = match value.parse::<f64>() {
+                    Ok(_) => value.clone(),
+                    Err(_) => path_source(&value.split('.').collect::<Vec<_>>()),
+                };
+                out.push_str(&format!("{} {} {}", path_source(field), op.symbol(), value))
+            }
+            FunctionPart::Expression(ast) if reads_as_field(ast) => {
+                out.push('(');
+                ast.write_source(source, out);
+                out.push(')');
+            }
+            FunctionPart::Expression(ast) => ast.write_source(source, out),
+        }
+    }
+}
+
+/// Whether an argument written without parentheses would parse as a field or filter
+/// rather than an expression, as `(a)` in `SUM((a); 1)`.
+fn reads_as_field(ast: &Ast) -> bool {
+    let field = |ast: &Ast| ast.path().is_some_and(|path| !path.contains('?'));
+    match &ast.kind {
+        AstKind::Binary(BinaryOperator::Compare(_), lhs, rhs) => {
+            field(lhs)
+                && (field(rhs)
+                    || matches!(
+                        rhs.kind,
+                        AstKind::Literal(ExpValue::Integer(_) | ExpValue::Number(_))
+                    ))
+        }
+        _ => field(ast),
+    }
+}
+
+impl Function {
+    fn write_source(&self, source: Option<&str>, out: &mut String) {
+        out.push_str(&self.name.to_uppercase());
+        out.push('(');
+        for (i, part) in self.parts.iter().enumerate() {
+            if i > 0 {
+                out.push_str("; ");
+            }
+            part.write_source(source, out);
+        }
+        out.push(')');
+    }
+}
+
+impl Ast {
+    /// Canonical source of the tree, see [`format`].
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        self.write_source(None, &mut out);
+        out
+    }
+
+    /// Literals without a value, such as `#2023-02-29#`, and numbers too large for a float,
+    /// such as `1e400`, are copied from `source` when given.
+    fn write_source(&self, source: Option<&str>, out: &mut String) {
+        match &self.kind {
+            AstKind::Literal(value)
+                if matches!(value, ExpValue::Error(_))
+                    || matches!(value, ExpValue::Number(n) if !n.is_finite()) =>
+            {
+                match source.and_then(|s| s.get(self.span.start..self.span.end)) {
+                    Some(text) => out.push_str(text.trim()),
+                    None => out.push_str(&literal_source(value)),
+                }
+            }
+            AstKind::Literal(value) => out.push_str(&literal_source(value)),
+            AstKind::Identifier(name) => out.push_str(&field_source(name)),
+            AstKind::Function(func) => func.write_source(source, out),
+            AstKind::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    item.write_source(source, out);
+                }
+                out.push(']');
+            }
+            AstKind::Index(target, index) => {
+                target.write_operand(source, out, u8::MAX);
+                out.push('[');
+                index.write_source(source, out);
+                out.push(']');
+            }
+            AstKind::Member(target, name, optional) => {
+                // `(1).x` keeps its parentheses, `1.x` does not parse.
+                if let AstKind::Literal(ExpValue::Integer(_) | ExpValue::Number(_)) = target.kind {
+                    out.push('(');
+                    target.write_source(source, out);
+                    out.push(')');
+                } else {
+                    target.write_operand(source, out, u8::MAX);
+                }
+                out.push_str(if *optional { "?." } else { "." });
+                out.push_str(&field_source(name));
+            }
+            AstKind::Binary(op, lhs, rhs) => {
+                let precedence = op.precedence();
+                let (left, right) = if op.is_right_associative() {
+                    (precedence + 1, precedence)
+                } else {
+                    (precedence, precedence + 1)
+                };
+                lhs.write_operand(source, out, left);
+                out.push_str(&format!(" {} ", op.symbol()));
+                rhs.write_operand(source, out, right);
+            }
+        }
+    }
+
+    /// Writes the node, in parentheses when it binds looser than `min_precedence`.
+    fn write_operand(&self, source: Option<&str>, out: &mut String, min_precedence: u8) {
+        let precedence = match &self.kind {
+            AstKind::Binary(op, _, _) => op.precedence(),
+            _ => u8::MAX,
+        };
+        if precedence < min_precedence {
+            out.push('(');
+            self.write_source(source, out);
+            out.push(')');
+        } else {
+            self.write_source(source, out);
+        }
+    }
+}
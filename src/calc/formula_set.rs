@@ -0,0 +1,188 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{Ast, EvalOptions, ExpValue, Locale};
+
+/// Formulas that read each other in a circle, `path` starts and ends with the same name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CycleError {
+    pub path: Vec<String>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message(Locale::En))
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+/// Named formulas that may read each other, e.g. formula fields of an issue type where
+/// `customfield_2018041` is `customfield_2018036 * 2` and `customfield_2018036` is a
+/// formula too.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormulaSet {
+    formulas: Vec<(String, Ast)>,
+    positions: HashMap<String, usize>,
+}
+
+impl FormulaSet {
+    pub fn new() -> FormulaSet {
+        FormulaSet::default()
+    }
+
+    pub fn formula(mut self, name: &str, ast: Ast) -> FormulaSet {
+        self.insert(name, ast);
+        self
+    }
+
+    /// Adds a formula, replacing the one of the same name. A cycle it closes is only
+    /// reported at evaluation, see [`FormulaSet::try_insert`].
+    pub fn insert(&mut self, name: &str, ast: Ast) {
+        match self.positions.get(name) {
+            Some(&i) => self.formulas[i].1 = ast,
+            None => {
+                self.positions.insert(name.to_string(), self.formulas.len());
+                self.formulas.push((name.to_string(), ast));
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Ast> {
+        self.positions.get(name).map(|&i| &self.formulas[i].1)
+    }
+
+    /// Names in order of insertion.
+    pub fn names(&self) -> Vec<&str> {
+        self.formulas
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Formulas of this set that `name` reads, in order of first appearance.
+    pub fn dependencies(&self, name: &str) -> Vec<String> {
+        match self.get(name) {
+            Some(ast) => reads(ast, &|name| self.get(name)),
+            None => vec![],
+        }
+    }
+
+    /// Every formula with the formulas of this set it reads. Fields that are not formulas of
+    /// the set are read from the table at evaluation and are not part of the graph.
+    pub fn graph(&self) -> HashMap<String, Vec<String>> {
+        self.formulas
+            .iter()
+            .map(|(name, ast)| (name.clone(), reads(ast, &|name| self.get(name))))
+            .collect()
+    }
+
+    /// Adds a formula unless it closes a cycle, e.g. saving `A` as `B + 1` while `B` reads `A`
+    /// fails with the cycle `A → B → A` and keeps the set as it was. Only the formulas the new
+    /// one reads are checked.
+    pub fn try_insert(&mut self, name: &str, ast: Ast) -> Result<(), CycleError> {
+        let formula = |n: &str| match n == name {
+            true => Some(&ast),
+            false => self.get(n),
+        };
+        visit(&formula, name, &mut HashSet::new(), &mut vec![])?;
+        self.insert(name, ast);
+        Ok(())
+    }
+
+    /// Names with every formula after the formulas it reads, or the first cycle found.
+    pub fn evaluation_order(&self) -> Result<Vec<String>, CycleError> {
+        let (mut done, mut order) = (HashSet::new(), vec![]);
+        for (name, _) in &self.formulas {
+            visit(&|name| self.get(name), name, &mut done, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    pub fn eval(
+        &self,
+        table: &HashMap<String, ExpValue>,
+    ) -> Result<HashMap<String, ExpValue>, CycleError> {
+        self.eval_with(table, &EvalOptions::default())
+    }
+
+    /// Values of all formulas, each evaluated against `table` and the values of the formulas
+    /// before it in [`FormulaSet::evaluation_order`].
+    pub fn eval_with(
+        &self,
+        table: &HashMap<String, ExpValue>,
+        options: &EvalOptions,
+    ) -> Result<HashMap<String, ExpValue>, CycleError> {
+        let mut table = table.clone();
+        let mut values = HashMap::new();
+        for name in self.evaluation_order()? {
+            if let Some(ast) = self.get(&name) {
+                let value = ast.eval_with(&table, options);
+                table.insert(name.clone(), value.clone());
+                values.insert(name, value);
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// Names `ast` reads that `formula` finds a formula for, in order of first appearance.
+fn reads<'a>(ast: &Ast, formula: &impl Fn(&str) -> Option<&'a Ast>) -> Vec<String> {
+    let mut names = vec![];
+    for dependency in ast.dependencies() {
+        if formula(&dependency.root).is_some() && !names.contains(&dependency.root) {
+            names.push(dependency.root);
+        }
+    }
+    names
+}
+
+/// Appends `start` and the formulas it reads that are not `done` yet to `order`, each after
+/// the formulas it reads. The path from `start` is kept on an explicit stack, so long chains
+/// do not exhaust the call stack and a cycle is reported with the names along it.
+fn visit<'a>(
+    formula: &impl Fn(&str) -> Option<&'a Ast>,
+    start: &str,
+    done: &mut HashSet<String>,
+    order: &mut Vec<String>,
+) -> Result<(), CycleError> {
+    if done.contains(start) {
+        return Ok(());
+    }
+    let pending = |name: &str| match formula(name) {
+        Some(ast) => reads(ast, formula).into_iter(),
+        None => vec![].into_iter(),
+    };
+    let mut path = vec![(start.to_string(), pending(start))];
+    let mut on_path = HashSet::from([start.to_string()]);
+    while let Some((_, dependencies)) = path.last_mut() {
+        match dependencies.next() {
+            Some(name) if done.contains(&name) => {}
+            Some(name) if on_path.contains(&name) => {
+                let start = path.iter().position(|(n, _)| *n == name).unwrap_or(0);
+                let mut cycle = path[start..]
+                    .iter()
+                    .map(|(n, _)| n.clone())
+                    .collect::<Vec<_>>();
+                cycle.push(name);
+                return Err(CycleError { path: cycle });
+            }
+            Some(name) => {
+                on_path.insert(name.clone());
+                let dependencies = pending(&name);
+                path.push((name, dependencies));
+            }
+            None => {
+                if let Some((name, _)) = path.pop() {
+                    on_path.remove(&name);
+                    done.insert(name.clone());
+                    order.push(name);
+                }
+            }
+        }
+    }
+    Ok(())
+}
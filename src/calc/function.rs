@@ -3,15 +3,26 @@ use std::collections::HashMap;
 use pest::iterators::Pair;
 use serde_json::Value;
 
-use crate::{calc::calc::Rule, ExpValue, Identifier};
+use crate::{
+    calc::{
+        ast::Dependencies,
+        calc::{field_name, Rule},
+    },
+    sum_values, walk_function, Ast, BinaryOperator, EvalOptions, ExpValue, Identifier, Span,
+    ValueError, Visitor, DEFAULT_EPSILON,
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Function {
     pub name: String,
     pub parts: Vec<FunctionPart>,
+    /// Source span of each entry in `parts`.
+    pub part_spans: Vec<Span>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompareOperator {
     Equal,
     NotEqual,
@@ -21,11 +32,65 @@ pub enum CompareOperator {
     LessThanOrEqual,
 }
 
+fn matches_filters(
+    item: &ExpValue,
+    filters: &[(&[String], CompareOperator, ExpValue)],
+    epsilon: f64,
+) -> bool {
+    filters
+        .iter()
+        .all(|(field, op, value)| op.test(&item.clone().member_path(field), value, epsilon))
+}
+
+/// Smallest or largest of the values, looking into arrays and skipping nulls.
+fn extreme(values: Vec<ExpValue>, max: bool, epsilon: f64) -> ExpValue {
+    let mut best: Option<ExpValue> = None;
+    for value in values {
+        let candidate = match value {
+            ExpValue::Array(items) => extreme(items, max, epsilon),
+            value => value,
+        };
+        best = match (candidate, best) {
+            (ExpValue::Error(e), _) => return ExpValue::Error(e),
+            (ExpValue::Null, best) => best,
+            (candidate, None) => Some(candidate),
+            (candidate, Some(best)) => match candidate.compare(&best, epsilon) {
+                None => return ExpValue::Error(ValueError::TypeMismatch),
+                Some(o) if (max && o.is_gt()) || (!max && o.is_lt()) => Some(candidate),
+                Some(_) => Some(best),
+            },
+        };
+    }
+    best.unwrap_or(ExpValue::Null)
+}
+
+/// The literal on the right of a filter, `4` in `status=4`.
+fn filter_value(value: &str) -> ExpValue {
+    if let Ok(n) = value.parse::<i64>() {
+        ExpValue::Integer(n)
+    } else if let Ok(n) = value.parse::<f64>() {
+        ExpValue::Number(n)
+    } else {
+        ExpValue::String(value.to_string())
+    }
+}
+
+/// Names of a dotted `function_parameter_ident`, `{...}` references without the braces.
+fn field_path(expression: Pair<Rule>) -> Vec<String> {
+    expression.into_inner().filter_map(field_name).collect()
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FunctionPart {
     Identifier(String),
-    IdentifierFWithField(String, String),
-    CompareExpression(String, CompareOperator, String),
+    /// A field and the names read on it, `subtask` and `["estimatePoint"]` in
+    /// `subtask.estimatePoint`. Names are kept apart since a `{...}` name may contain dots.
+    IdentifierFWithField(String, Vec<String>),
+    /// `status=4`, the field path is read on the items of the collection.
+    CompareExpression(Vec<String>, CompareOperator, String),
+    /// Any other argument, e.g. the `0` in `COALESCE(a; 0)`.
+    Expression(Ast),
 }
 
 impl FunctionPart {
@@ -33,9 +98,7 @@ impl FunctionPart {
         if let Rule::function_parameter = expression.as_rule() {
             return expression
                 .into_inner()
-                .map(FunctionPart::from_single)
-                .filter(|p| p.is_some())
-                .map(|p| p.unwrap())
+                .filter_map(FunctionPart::from_single)
                 .collect::<Vec<FunctionPart>>();
         }
         vec![]
@@ -44,81 +107,66 @@ impl FunctionPart {
     fn from_single(expression: Pair<Rule>) -> Option<FunctionPart> {
         if Rule::function_parameter_item == expression.as_rule() {
             let inner = expression.into_inner().collect::<Vec<Pair<Rule>>>();
-            if inner.len() == 1 {
-                let str = inner[0].as_str().trim();
-                return str
-                    .split(".")
-                    .map(|s| s.to_string())
-                    .collect::<Vec<String>>()
-                    .as_slice()
-                    .split_first()
-                    .map(|(first, rest)| {
+            if inner.len() == 1 && inner[0].as_rule() == Rule::expr {
+                return Ast::from(inner[0].clone()).map(FunctionPart::Expression);
+            } else if inner.len() == 1 {
+                return field_path(inner[0].clone()).as_slice().split_first().map(
+                    |(first, rest)| {
                         if rest.is_empty() {
                             FunctionPart::Identifier(first.to_string())
                         } else {
-                            FunctionPart::IdentifierFWithField(
-                                first.to_string(),
-                                rest[0].to_string(),
-                            )
+                            FunctionPart::IdentifierFWithField(first.to_string(), rest.to_vec())
                         }
-                    });
+                    },
+                );
             } else if inner.len() == 2 {
                 let mut compare_expression = inner[1].clone().into_inner();
 
-                let compare_operator = match compare_expression.next().unwrap().as_rule() {
-                    Rule::compare_eq => CompareOperator::Equal,
-                    _ => return None,
-                };
+                let compare_operator =
+                    CompareOperator::from(compare_expression.next().unwrap().as_rule())?;
 
+                let value = compare_expression.next().unwrap();
                 return Some(FunctionPart::CompareExpression(
-                    inner[0].as_str().to_string(),
+                    field_path(inner[0].clone()),
                     compare_operator,
-                    compare_expression.next().unwrap().as_str().to_string(),
+                    match value.as_rule() {
+                        Rule::function_parameter_ident => field_path(value).join("."),
+                        _ => value.as_str().to_string(),
+                    },
                 ));
             }
-
-            for pair in inner {
-                match pair.as_rule() {
-                    Rule::function_parameter_ident => {
-                        return Some(FunctionPart::Identifier(pair.as_str().to_string()));
-                    }
-                    Rule::function_parameter_compare => {
-                        let mut compare_expression = pair.into_inner();
-                        let left = compare_expression.next().unwrap().as_str().to_string();
-                        let operator = match compare_expression.next().unwrap().as_rule() {
-                            Rule::compare_eq => CompareOperator::Equal,
-                            // Rule::function_parameter_compare_not_equal => CompareOperator::NotEqual,
-                            // Rule::function_parameter_compare_greater_than => {
-                            //     CompareOperator::GreaterThan
-                            // }
-                            // Rule::function_parameter_compare_greater_than_or_equal => {
-                            //     CompareOperator::GreaterThanOrEqual
-                            // }
-                            // Rule::function_parameter_compare_less_than => CompareOperator::LessThan,
-                            // Rule::function_parameter_compare_less_than_or_equal => {
-                            //     CompareOperator::LessThanOrEqual
-                            // }
-                            _ => return None,
-                        };
-                        let right = compare_expression.next().unwrap().as_str().to_string();
-                        return Some(FunctionPart::CompareExpression(left, operator, right));
-                    }
-                    _ => return None,
-                }
-            }
         }
         None
     }
 
     pub fn get_dependencies(&self) -> Vec<Identifier> {
+        let mut dependencies = Dependencies::default();
+        dependencies.visit_function_part(self, Span::default());
+        dependencies.0
+    }
+
+    pub fn eval_with(&self, table: &HashMap<String, ExpValue>, options: &EvalOptions) -> ExpValue {
         match self {
-            FunctionPart::Identifier(ident) => vec![Identifier {
-                name: ident.clone(),
-            }],
-            FunctionPart::IdentifierFWithField(ident, _) => vec![Identifier {
-                name: ident.clone(),
-            }],
-            _ => vec![],
+            FunctionPart::Identifier(ident) => table
+                .get(ident)
+                .cloned()
+                .unwrap_or(ExpValue::Error(ValueError::UnknownIdentifier)),
+            FunctionPart::IdentifierFWithField(ident, path) => table
+                .get(ident)
+                .cloned()
+                .map(|value| value.member_path(path))
+                .unwrap_or(ExpValue::Error(ValueError::UnknownIdentifier)),
+            FunctionPart::Expression(ast) => ast.eval_with(table, options),
+            FunctionPart::CompareExpression(field, op, value) => {
+                let lhs = match field.split_first() {
+                    Some((root, path)) => match table.get(root) {
+                        Some(value) => value.clone().member_path(path),
+                        None => ExpValue::Error(ValueError::UnknownIdentifier),
+                    },
+                    None => ExpValue::Error(ValueError::InvalidArguments),
+                };
+                op.apply(&lhs, &filter_value(value), options.epsilon)
+            }
         }
     }
 }
@@ -127,7 +175,8 @@ impl Function {
     pub fn from(expression: Pair<Rule>) -> Option<Function> {
         if let Rule::function = expression.as_rule() {
             let mut fun_name: Option<String> = None;
-            let mut parts: Option<Vec<FunctionPart>> = None;
+            let mut parts = vec![];
+            let mut part_spans = vec![];
 
             for pair in expression.into_inner() {
                 match pair.as_rule() {
@@ -135,7 +184,13 @@ impl Function {
                         fun_name = Some(pair.as_str().to_string());
                     }
                     Rule::function_parameter => {
-                        parts = Some(FunctionPart::from(pair));
+                        for item in pair.into_inner() {
+                            let span = Span::from(item.as_span());
+                            if let Some(part) = FunctionPart::from_single(item) {
+                                parts.push(part);
+                                part_spans.push(span);
+                            }
+                        }
                     }
                     _ => continue,
                 }
@@ -143,7 +198,8 @@ impl Function {
             if let Some(name) = fun_name {
                 return Some(Function {
                     name,
-                    parts: parts.unwrap_or(vec![]),
+                    parts,
+                    part_spans,
                 });
             }
             None
@@ -153,46 +209,142 @@ impl Function {
     }
 
     pub fn get_dependencies(&self) -> Vec<Identifier> {
+        let mut dependencies = Dependencies::default();
+        walk_function(&mut dependencies, self);
+        dependencies.0
+    }
+
+    /// Evaluates the function against an expression table. Arguments of `COALESCE`
+    /// are evaluated lazily, up to the first one that is not null.
+    pub fn eval_with(&self, table: &HashMap<String, ExpValue>, options: &EvalOptions) -> ExpValue {
+        let mut args = self.parts.iter().map(|part| part.eval_with(table, options));
+        match (self.name.to_lowercase().as_str(), self.parts.len()) {
+            ("coalesce", _) | ("ifnull", 2) => args
+                .find(|value| !value.is_null())
+                .unwrap_or(ExpValue::Null),
+            ("isblank", 1) => ExpValue::Bool(args.all(|value| value.is_null())),
+            ("iserror", 1) => ExpValue::Bool(args.all(|value| value.is_error())),
+            ("sum", _) => match self.collection(table, options) {
+                // added as `+` does, so decimal mode and coercion apply to the total too
+                ExpValue::Array(items) => items
+                    .into_iter()
+                    .filter(|value| !value.is_null())
+                    .fold(ExpValue::Integer(0), |sum, value| {
+                        BinaryOperator::Add.apply_with(sum, value, options)
+                    }),
+                ExpValue::Null => ExpValue::Null,
+                ExpValue::Error(e) => ExpValue::Error(e),
+                _ => ExpValue::Error(ValueError::TypeMismatch),
+            },
+            ("count", _) => match self.collection(table, options) {
+                ExpValue::Array(items) => ExpValue::Integer(items.len() as i64),
+                ExpValue::Null => ExpValue::Integer(0),
+                ExpValue::Error(e) => ExpValue::Error(e),
+                _ => ExpValue::Error(ValueError::TypeMismatch),
+            },
+            ("min" | "max", _) => {
+                let values = match self.filters() {
+                    Some(_) if self.parts.len() > 1 => vec![self.collection(table, options)],
+                    _ => args.collect(),
+                };
+                extreme(
+                    values,
+                    self.name.eq_ignore_ascii_case("max"),
+                    options.epsilon,
+                )
+            }
+            ("ifnull" | "isblank" | "iserror", _) => ExpValue::Error(ValueError::InvalidArguments),
+            _ => ExpValue::Error(ValueError::Unsupported),
+        }
+    }
+
+    /// The first argument of an aggregate, keeping only the elements that match every
+    /// `field=value` filter, e.g. `subtask.estimatePoint;status=4`.
+    fn collection(&self, table: &HashMap<String, ExpValue>, options: &EvalOptions) -> ExpValue {
+        let (first, filters) = match self.parts.split_first() {
+            Some(parts) => parts,
+            None => return ExpValue::Error(ValueError::InvalidArguments),
+        };
+        if filters.is_empty() {
+            return first.eval_with(table, options);
+        }
+        let (root, path) = match first {
+            FunctionPart::Identifier(root) => (root, None),
+            FunctionPart::IdentifierFWithField(root, path) => (root, Some(path)),
+            _ => return ExpValue::Error(ValueError::InvalidArguments),
+        };
+        let items = match table.get(root) {
+            Some(ExpValue::Array(items)) => items,
+            Some(ExpValue::Null) => return ExpValue::Null,
+            Some(ExpValue::Error(e)) => return ExpValue::Error(*e),
+            Some(_) => return ExpValue::Error(ValueError::TypeMismatch),
+            None => return ExpValue::Error(ValueError::UnknownIdentifier),
+        };
+
+        let filters = match self.filters() {
+            Some(filters) => filters,
+            None => return ExpValue::Error(ValueError::InvalidArguments),
+        };
+
+        let mut selected = vec![];
+        for item in items {
+            if matches_filters(item, &filters, options.epsilon) {
+                selected.push(match path {
+                    Some(path) => item.clone().member_path(path),
+                    None => item.clone(),
+                });
+            }
+        }
+        ExpValue::Array(selected)
+    }
+
+    /// The `field=value` filters after the first argument, `None` when other kinds of
+    /// arguments are among them.
+    fn filters(&self) -> Option<Vec<(&[String], CompareOperator, ExpValue)>> {
         self.parts
             .iter()
-            .flat_map(|p| p.get_dependencies())
+            .skip(1)
+            .map(|part| match part {
+                FunctionPart::CompareExpression(field, op, value) => {
+                    Some((field.as_slice(), *op, filter_value(value)))
+                }
+                _ => None,
+            })
             .collect()
     }
 
+    /// Elements of the JSON array named by the first argument that match the filters.
+    fn json_collection<'a>(&self, json: &'a Value) -> Option<Vec<&'a Value>> {
+        let data = match self.parts.first() {
+            Some(FunctionPart::Identifier(ident)) => json.as_object()?.get(ident)?.as_array()?,
+            _ => return None,
+        };
+        let filters = self.filters()?;
+        Some(
+            data.iter()
+                .filter(|item| {
+                    matches_filters(&ExpValue::from((*item).clone()), &filters, DEFAULT_EPSILON)
+                })
+                .collect(),
+        )
+    }
+
     pub fn run(&self, json: &Value) -> Option<ExpValue> {
         match self.name.to_lowercase().as_str() {
             "sum" => {
-                let data = match self.parts.get(0) {
-                    Some(FunctionPart::Identifier(ident)) => {
-                        json.as_object().unwrap().get(ident)?.as_array().clone()
-                    }
-                    // Some(FunctionPart::IdentifierFWithField(ident, field)) => {
-                    //     for (_, value) in table {
-                    //         if let Some(value) = value.get(ident) {
-                    //             if let Some(value) = value.get(field) {
-                    //                 if let Some(value) = value.as_f64() {
-                    //                     sum += value;
-                    //                 }
-                    //             }
-                    //         }
-                    //     }
-                    // }
-                    _ => return None,
-                };
-
-                match data {
-                    Some(data) => {
-                        let mut sum = 0.0;
-                        for value in data {
-                            if let Some(value) = value.as_object()?.get("id")?.as_f64() {
-                                sum += value;
-                            }
-                        }
-                        Some(ExpValue::Number(sum))
+                let data = self.json_collection(json)?;
+                let mut values = vec![];
+                for value in data {
+                    let value = value.as_object()?.get("id")?;
+                    if let Some(value) = value.as_i64() {
+                        values.push(ExpValue::Integer(value));
+                    } else if let Some(value) = value.as_f64() {
+                        values.push(ExpValue::Number(value));
                     }
-                    None => None,
                 }
+                Some(sum_values(values))
             }
+            "count" => Some(ExpValue::Integer(self.json_collection(json)?.len() as i64)),
 
             // for part in &self.parts {
             //     if let FunctionPart::Identifier(ident) = part {
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Number, Value};
+
+use crate::{format_date_literal, ExpValue};
+
+/// Integers that fit an `i64` become `Integer`, other numbers `Number`. With the `decimal`
+/// feature integers up to `u64::MAX` become an exact `Decimal`, without it they are rounded
+/// to the nearest `Number`.
+impl From<Value> for ExpValue {
+    fn from(value: Value) -> ExpValue {
+        match value {
+            Value::Null => ExpValue::Null,
+            Value::Bool(b) => ExpValue::Bool(b),
+            Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(n), _) => ExpValue::Integer(n),
+                #[cfg(feature = "decimal")]
+                (None, Some(n)) => ExpValue::Decimal(n.into()),
+                _ => ExpValue::Number(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            Value::String(s) => ExpValue::String(s),
+            Value::Array(items) => ExpValue::Array(items.into_iter().map(ExpValue::from).collect()),
+            Value::Object(fields) => ExpValue::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name, ExpValue::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// JSON has no dates, durations or exact decimals, so they are written in the form the API
+/// uses and read back as that form: a date becomes a `2024-03-01T00:00:00Z` string that
+/// [`parse_date_literal`](crate::parse_date_literal) reads back, a duration its number of
+/// milliseconds and a decimal a string with all of its digits, `"0.30"`, unless it is a
+/// whole number that fits a JSON integer. Errors and non-finite numbers have no JSON form
+/// and become `null`. Every other value converts back to itself.
+impl From<ExpValue> for Value {
+    fn from(value: ExpValue) -> Value {
+        match value {
+            ExpValue::Error(_) | ExpValue::Null => Value::Null,
+            ExpValue::Bool(b) => Value::Bool(b),
+            ExpValue::Number(n) => Number::from_f64(n)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            ExpValue::Integer(n) => Value::Number(n.into()),
+            ExpValue::String(s) => Value::String(s),
+            ExpValue::Array(items) => Value::Array(items.into_iter().map(Value::from).collect()),
+            ExpValue::Object(fields) => Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name, Value::from(value)))
+                    .collect::<Map<String, Value>>(),
+            ),
+            ExpValue::Date(millis) => Value::String(format_date_literal(millis)),
+            ExpValue::Duration(millis) => Value::Number(millis.into()),
+            #[cfg(feature = "decimal")]
+            ExpValue::Decimal(d) => {
+                use rust_decimal::prelude::ToPrimitive;
+                match (d.scale(), d.to_i64(), d.to_u64()) {
+                    (0, Some(n), _) => Value::Number(n.into()),
+                    (0, None, Some(n)) => Value::Number(n.into()),
+                    _ => Value::String(d.to_string()),
+                }
+            }
+        }
+    }
+}
+
+/// Expression table from the fields of a JSON object, e.g. an issue returned by the API.
+pub fn table_from_json(json: Value) -> HashMap<String, ExpValue> {
+    match json {
+        Value::Object(fields) => fields
+            .into_iter()
+            .map(|(name, value)| (name, ExpValue::from(value)))
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
@@ -0,0 +1,336 @@
+use crate::{
+    CycleError, Diagnostic, DiagnosticKind, Expected, FieldType, Locale, ParseError, RenameError,
+    TypeError, TypeErrorKind, ValueError, ValueType,
+};
+
+// Error codes are part of the public interface: frontends map them to their own texts,
+// so a code is never reused or renumbered. `P` codes are parse errors, `T` type errors,
+// `S` schema diagnostics, `V` evaluation errors, `F` errors of formula sets and `N` errors
+// of field renaming.
+
+/// `a, b or c` in English, `a、b或c` in Chinese.
+fn join_alternatives(items: &[String], locale: Locale) -> String {
+    let (separator, last) = match locale {
+        Locale::En => (", ", " or "),
+        Locale::ZhCn => ("、", "或"),
+    };
+    match items.split_last() {
+        None => String::new(),
+        Some((only, [])) => only.clone(),
+        Some((last_item, rest)) => format!("{}{}{}", rest.join(separator), last, last_item),
+    }
+}
+
+impl ValueType {
+    /// [`ValueType::name`] in the words of `locale`, `Array<Number>` is `数字列表` in Chinese.
+    pub fn localized_name(&self, locale: Locale) -> String {
+        if locale == Locale::En {
+            return self.name();
+        }
+        match self {
+            ValueType::Any => "任意值".to_string(),
+            ValueType::Bool => "布尔值".to_string(),
+            ValueType::Number => "数字".to_string(),
+            ValueType::String => "文本".to_string(),
+            ValueType::Date => "日期".to_string(),
+            ValueType::Duration => "时长".to_string(),
+            ValueType::Array(item) => format!("{}列表", item.localized_name(locale)),
+            ValueType::Object(fields) => format!(
+                "{{{}}}",
+                fields
+                    .iter()
+                    .map(|(name, value_type)| {
+                        format!("{}: {}", name, value_type.localized_name(locale))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl FieldType {
+    pub fn localized_name(&self, locale: Locale) -> &'static str {
+        match (locale, self) {
+            (Locale::En, field_type) => field_type.name(),
+            (Locale::ZhCn, FieldType::Integer) => "整数",
+            (Locale::ZhCn, FieldType::Decimal) => "小数",
+            (Locale::ZhCn, FieldType::PercentageNumber) => "百分比",
+            (Locale::ZhCn, FieldType::PercentageBar) => "百分比进度条",
+            (Locale::ZhCn, FieldType::Date) => "日期",
+            (Locale::ZhCn, FieldType::DateTime) => "日期时间",
+        }
+    }
+}
+
+impl Expected {
+    pub fn message(&self, locale: Locale) -> String {
+        match (locale, self) {
+            (_, Expected::Close(c)) => format!("`{}`", c),
+            (Locale::En, Expected::Value) => "a value".to_string(),
+            (Locale::En, Expected::Field) => "a field name".to_string(),
+            (Locale::En, Expected::Number) => "a number".to_string(),
+            (Locale::En, Expected::Date) => "a date".to_string(),
+            (Locale::En, Expected::Duration) => "a duration".to_string(),
+            (Locale::En, Expected::List) => "a list".to_string(),
+            (Locale::En, Expected::Operator) => "an operator".to_string(),
+            (Locale::En, Expected::Accessor) => "a member or index access".to_string(),
+            (Locale::En, Expected::End) => "the end of the formula".to_string(),
+            (Locale::ZhCn, Expected::Value) => "值".to_string(),
+            (Locale::ZhCn, Expected::Field) => "字段名".to_string(),
+            (Locale::ZhCn, Expected::Number) => "数字".to_string(),
+            (Locale::ZhCn, Expected::Date) => "日期".to_string(),
+            (Locale::ZhCn, Expected::Duration) => "时长".to_string(),
+            (Locale::ZhCn, Expected::List) => "列表".to_string(),
+            (Locale::ZhCn, Expected::Operator) => "运算符".to_string(),
+            (Locale::ZhCn, Expected::Accessor) => "成员或下标访问".to_string(),
+            (Locale::ZhCn, Expected::End) => "公式结尾".to_string(),
+        }
+    }
+}
+
+impl ParseError {
+    pub fn code(&self) -> &'static str {
+        match self.found {
+            Some(_) => "P001",
+            None => "P002",
+        }
+    }
+
+    pub fn message(&self, locale: Locale) -> String {
+        let expected = self
+            .expected
+            .iter()
+            .map(|e| e.message(locale))
+            .collect::<Vec<_>>();
+        match locale {
+            Locale::En => format!(
+                "expected {} at {}:{}, found {}",
+                match expected.is_empty() {
+                    true => "a valid formula".to_string(),
+                    false => join_alternatives(&expected, locale),
+                },
+                self.line,
+                self.column,
+                match self.found {
+                    Some(c) => format!("`{}`", c),
+                    None => "the end of the formula".to_string(),
+                }
+            ),
+            Locale::ZhCn => format!(
+                "第 {} 行第 {} 列应为{}，{}",
+                self.line,
+                self.column,
+                match expected.is_empty() {
+                    true => "有效的公式".to_string(),
+                    false => join_alternatives(&expected, locale),
+                },
+                match self.found {
+                    Some(c) => format!("实际为 `{}`", c),
+                    None => "但公式已结束".to_string(),
+                }
+            ),
+        }
+    }
+}
+
+impl TypeErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TypeErrorKind::InvalidOperands(..) => "T001",
+            TypeErrorKind::FieldTypeMismatch(..) => "T002",
+            TypeErrorKind::InvalidIndex(..) => "T003",
+        }
+    }
+
+    pub fn message(&self, locale: Locale) -> String {
+        let name = |t: &ValueType| t.localized_name(locale);
+        match (locale, self) {
+            (Locale::En, TypeErrorKind::InvalidOperands(op, lhs, rhs)) => format!(
+                "cannot apply `{}` to {} and {}",
+                op.symbol(),
+                name(lhs),
+                name(rhs)
+            ),
+            (Locale::En, TypeErrorKind::InvalidIndex(target, index)) => {
+                format!("cannot index {} with {}", name(target), name(index))
+            }
+            (Locale::En, TypeErrorKind::FieldTypeMismatch(field_type, found)) => format!(
+                "formula produces {} but the field is {}",
+                name(found),
+                field_type.localized_name(locale)
+            ),
+            (Locale::ZhCn, TypeErrorKind::InvalidOperands(op, lhs, rhs)) => {
+                format!("无法对{}和{}使用 `{}`", name(lhs), name(rhs), op.symbol())
+            }
+            (Locale::ZhCn, TypeErrorKind::InvalidIndex(target, index)) => {
+                format!("无法用{}作为{}的下标", name(index), name(target))
+            }
+            (Locale::ZhCn, TypeErrorKind::FieldTypeMismatch(field_type, found)) => format!(
+                "公式结果为{}，但字段类型是{}",
+                name(found),
+                field_type.localized_name(locale)
+            ),
+        }
+    }
+}
+
+impl TypeError {
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    pub fn message(&self, locale: Locale) -> String {
+        self.kind.message(locale)
+    }
+}
+
+impl DiagnosticKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DiagnosticKind::UnknownIdentifier(_) => "S001",
+            DiagnosticKind::UnknownField(_) => "S002",
+            DiagnosticKind::NotACollection(..) => "S003",
+            DiagnosticKind::IncompatibleField(..) => "S004",
+            DiagnosticKind::InvalidFilterValue(..) => "S005",
+            DiagnosticKind::Type(kind) => kind.code(),
+        }
+    }
+
+    pub fn message(&self, locale: Locale) -> String {
+        let name = |t: &ValueType| t.localized_name(locale);
+        match (locale, self) {
+            (_, DiagnosticKind::Type(kind)) => kind.message(locale),
+            (Locale::En, DiagnosticKind::UnknownIdentifier(path))
+            | (Locale::En, DiagnosticKind::UnknownField(path)) => {
+                format!("unknown field `{}`", path)
+            }
+            (Locale::En, DiagnosticKind::NotACollection(path, found)) => {
+                format!("`{}` is {}, not a collection", path, name(found))
+            }
+            (Locale::En, DiagnosticKind::IncompatibleField(path, expected, found)) => format!(
+                "`{}` is {} but {} is required",
+                path,
+                name(found),
+                name(expected)
+            ),
+            (Locale::En, DiagnosticKind::InvalidFilterValue(path, field_type, value)) => format!(
+                "`{}` cannot be compared with `{}`, it is {}",
+                path,
+                value,
+                name(field_type)
+            ),
+            (Locale::ZhCn, DiagnosticKind::UnknownIdentifier(path))
+            | (Locale::ZhCn, DiagnosticKind::UnknownField(path)) => {
+                format!("未知字段 `{}`", path)
+            }
+            (Locale::ZhCn, DiagnosticKind::NotACollection(path, found)) => {
+                format!("`{}` 是{}，不是集合", path, name(found))
+            }
+            (Locale::ZhCn, DiagnosticKind::IncompatibleField(path, expected, found)) => {
+                format!("`{}` 是{}，此处需要{}", path, name(found), name(expected))
+            }
+            (Locale::ZhCn, DiagnosticKind::InvalidFilterValue(path, field_type, value)) => {
+                format!("`{}` 是{}，不能与 `{}` 比较", path, name(field_type), value)
+            }
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    pub fn message(&self, locale: Locale) -> String {
+        self.kind.message(locale)
+    }
+}
+
+impl ValueError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ValueError::TypeMismatch => "V001",
+            ValueError::UnknownIdentifier => "V002",
+            ValueError::InvalidLiteral => "V003",
+            ValueError::DivByZero => "V004",
+            ValueError::NaN => "V005",
+            ValueError::Overflow => "V006",
+            ValueError::Unsupported => "V007",
+            ValueError::InvalidArguments => "V008",
+            ValueError::LengthMismatch => "V009",
+            ValueError::NullMember => "V010",
+        }
+    }
+
+    pub fn message(&self, locale: Locale) -> &'static str {
+        match (locale, self) {
+            (Locale::En, ValueError::TypeMismatch) => "values of these types cannot be combined",
+            (Locale::En, ValueError::UnknownIdentifier) => "unknown field",
+            (Locale::En, ValueError::InvalidLiteral) => "invalid date or duration",
+            (Locale::En, ValueError::DivByZero) => "division by zero",
+            (Locale::En, ValueError::NaN) => "the result is not a number",
+            (Locale::En, ValueError::Overflow) => "the result is too large",
+            (Locale::En, ValueError::Unsupported) => "unsupported function or operation",
+            (Locale::En, ValueError::InvalidArguments) => "wrong number of arguments",
+            (Locale::En, ValueError::LengthMismatch) => "lists of different lengths",
+            (Locale::En, ValueError::NullMember) => "member access on an empty value",
+            (Locale::ZhCn, ValueError::TypeMismatch) => "这些类型的值不能一起计算",
+            (Locale::ZhCn, ValueError::UnknownIdentifier) => "未知字段",
+            (Locale::ZhCn, ValueError::InvalidLiteral) => "无效的日期或时长",
+            (Locale::ZhCn, ValueError::DivByZero) => "除数为零",
+            (Locale::ZhCn, ValueError::NaN) => "结果不是数字",
+            (Locale::ZhCn, ValueError::Overflow) => "结果超出范围",
+            (Locale::ZhCn, ValueError::Unsupported) => "不支持的函数或运算",
+            (Locale::ZhCn, ValueError::InvalidArguments) => "参数个数不正确",
+            (Locale::ZhCn, ValueError::LengthMismatch) => "列表长度不一致",
+            (Locale::ZhCn, ValueError::NullMember) => "不能访问空值的成员",
+        }
+    }
+}
+
+impl CycleError {
+    pub fn code(&self) -> &'static str {
+        "F001"
+    }
+
+    /// `A → B → A` after a short explanation.
+    pub fn message(&self, locale: Locale) -> String {
+        let path = self.path.join(" → ");
+        match locale {
+            Locale::En => format!("formulas reference each other: {}", path),
+            Locale::ZhCn => format!("公式循环引用：{}", path),
+        }
+    }
+}
+
+impl RenameError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RenameError::Parse(error) => error.code(),
+            RenameError::InvalidName(_) => "N001",
+            RenameError::DuplicateName(_) => "N002",
+        }
+    }
+
+    pub fn message(&self, locale: Locale) -> String {
+        match (locale, self) {
+            (_, RenameError::Parse(error)) => error.message(locale),
+            (Locale::En, RenameError::InvalidName(name)) => {
+                format!(
+                    "`{}` cannot be written as a field name",
+                    name.escape_debug()
+                )
+            }
+            (Locale::En, RenameError::DuplicateName(name)) => {
+                format!("several fields are named `{}`", name)
+            }
+            (Locale::ZhCn, RenameError::InvalidName(name)) => {
+                format!("`{}` 不能用作字段名", name.escape_debug())
+            }
+            (Locale::ZhCn, RenameError::DuplicateName(name)) => {
+                format!("多个字段同名 `{}`", name)
+            }
+        }
+    }
+}
@@ -1,9 +1,47 @@
+mod ast;
+#[allow(clippy::module_inception)]
 mod calc;
+mod coercion;
+#[cfg(feature = "decimal")]
+mod decimal;
+mod dependencies;
+mod display;
+mod expressions;
+mod field_format;
+mod format;
+mod formula_set;
 mod function;
+mod json;
+mod messages;
+mod names;
+mod optimize;
+mod options;
+mod ordering;
+mod parse_error;
+mod schema;
+mod time;
+mod types;
 mod value;
-mod expressions;
+mod visit;
 
-pub use function::*;
+pub use ast::*;
 pub use calc::*;
+pub use coercion::*;
+#[cfg(feature = "decimal")]
+pub use decimal::*;
+pub use dependencies::*;
 pub use expressions::*;
-pub use value::*;
\ No newline at end of file
+pub use field_format::*;
+pub use format::*;
+pub use formula_set::*;
+pub use function::*;
+pub use json::*;
+pub use names::*;
+pub use options::*;
+pub use ordering::*;
+pub use parse_error::*;
+pub use schema::*;
+pub use time::*;
+pub use types::*;
+pub use value::*;
+pub use visit::*;
@@ -0,0 +1,214 @@
+use std::{collections::HashMap, fmt};
+
+use pest::{iterators::Pairs, Parser};
+
+use crate::{
+    calc::{
+        calc::{field_name, Calculator, Rule},
+        format::field_source,
+    },
+    Ast, FunctionPart, Locale, ParseError, Span, Visitor, VisitorMut,
+};
+
+struct NameResolver<'a>(&'a HashMap<String, String>);
+
+impl VisitorMut for NameResolver<'_> {
+    fn visit_identifier_mut(&mut self, name: &mut String, _span: Span) {
+        if let Some(id) = self.0.get(name) {
+            *name = id.clone();
+        }
+    }
+
+    fn visit_member_mut(&mut self, name: &mut String, span: Span) {
+        self.visit_identifier_mut(name, span)
+    }
+
+    fn visit_filter_field_mut(&mut self, path: &mut [String], span: Span) {
+        for name in path {
+            self.visit_identifier_mut(name, span);
+        }
+    }
+}
+
+impl FunctionPart {
+    pub fn resolve_names(&self, names: &HashMap<String, String>) -> FunctionPart {
+        let mut part = self.clone();
+        NameResolver(names).visit_function_part_mut(&mut part, Span::default());
+        part
+    }
+}
+
+impl Ast {
+    /// Replaces field names, e.g. the display name in `{故事价值} * 2`, by the ids `names`
+    /// maps them to. Names without an entry are kept, filter values are not fields and
+    /// are kept too.
+    pub fn resolve_names(&self, names: &HashMap<String, String>) -> Ast {
+        let mut ast = self.clone();
+        ast.visit_mut(&mut NameResolver(names));
+        ast
+    }
+}
+
+/// A field name as written in a formula, `span` covers the braces of a `{...}` reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldReference {
+    pub name: String,
+    pub span: Span,
+}
+
+/// Field names of the `rule` match at `start` in `source`, e.g. the `function_parameter_ident`
+/// that starts a function argument or the `member` after a value.
+fn references_at(source: &str, start: usize, rule: Rule) -> Vec<FieldReference> {
+    match Calculator::parse(rule, &source[start..]) {
+        Ok(pairs) => references_in(pairs, start),
+        Err(_) => vec![],
+    }
+}
+
+fn references_in(pairs: Pairs<Rule>, start: usize) -> Vec<FieldReference> {
+    pairs
+        .flatten()
+        .filter(|pair| matches!(pair.as_rule(), Rule::ident | Rule::reference))
+        .filter_map(|pair| {
+            let span = Span {
+                start: start + pair.as_span().start(),
+                end: start + pair.as_span().end(),
+            };
+            field_name(pair).map(|name| FieldReference { name, span })
+        })
+        .collect()
+}
+
+struct ReferenceCollector<'a> {
+    source: &'a str,
+    references: Vec<FieldReference>,
+}
+
+impl Visitor for ReferenceCollector<'_> {
+    fn visit_identifier(&mut self, _name: &str, span: Span) {
+        // a field argument passes its whole span, which starts with the root name
+        self.extend(references_at(
+            self.source,
+            span.start,
+            Rule::function_parameter_ident,
+        ));
+    }
+
+    fn visit_member(&mut self, _name: &str, span: Span) {
+        // The name has no span of its own and a `{...}` name may contain dots, so take the
+        // first `.name` that ends where the member expression ends, `?.` included.
+        let accessor = (span.start..span.end).find_map(|start| {
+            let pairs = Calculator::parse(Rule::member, self.source.get(start..span.end)?).ok()?;
+            match pairs.as_str().len() == span.end - start {
+                true => Some(references_in(pairs, start)),
+                false => None,
+            }
+        });
+        self.extend(accessor.unwrap_or_default());
+    }
+
+    fn visit_filter_field(&mut self, _path: &[String], span: Span) {
+        self.visit_identifier("", span)
+    }
+}
+
+impl ReferenceCollector<'_> {
+    /// Adds the references not found yet, the names of `a.b` are found from both `a` and `.b`.
+    fn extend(&mut self, references: Vec<FieldReference>) {
+        for reference in references {
+            if !self.references.contains(&reference) {
+                self.references.push(reference);
+            }
+        }
+    }
+}
+
+impl Ast {
+    /// Every field name in `source`, the formula this tree was parsed from, in source order.
+    /// Function names and filter values such as `DONE` in `status=DONE` are not fields.
+    pub fn field_references(&self, source: &str) -> Vec<FieldReference> {
+        let mut collector = ReferenceCollector {
+            source,
+            references: vec![],
+        };
+        self.visit(&mut collector);
+        let mut references = collector.references;
+        references.sort_by_key(|reference| reference.span.start);
+        references
+    }
+}
+
+/// Why field names could not be replaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RenameError {
+    Parse(ParseError),
+    /// A name no `{...}` reference can hold: empty, or with a `}` or a line break.
+    InvalidName(String),
+    /// A display name given to several ids, so [`names_to_ids`] cannot tell which one is meant.
+    DuplicateName(String),
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message(Locale::En))
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+impl From<ParseError> for RenameError {
+    fn from(error: ParseError) -> RenameError {
+        RenameError::Parse(error)
+    }
+}
+
+/// Whether `{name}` reads back as `name`.
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['}', '\n', '\r'])
+}
+
+/// Replaces every field name of `source` that `names` maps, keeping all other text as is.
+/// New names are written bare when they read as identifiers.
+pub fn rename_fields(source: &str, names: &HashMap<String, String>) -> Result<String, RenameError> {
+    rewrite_fields(source, names, field_source)
+}
+
+fn rewrite_fields(
+    source: &str,
+    names: &HashMap<String, String>,
+    write: impl Fn(&str) -> String,
+) -> Result<String, RenameError> {
+    let mut out = String::new();
+    let mut end = 0;
+    for reference in Ast::parse(source)?.field_references(source) {
+        if let Some(name) = names.get(&reference.name) {
+            if !valid_name(name) {
+                return Err(RenameError::InvalidName(name.clone()));
+            }
+            out.push_str(&source[end..reference.span.start]);
+            out.push_str(&write(name));
+            end = reference.span.end;
+        }
+    }
+    out.push_str(&source[end..]);
+    Ok(out)
+}
+
+/// Id form to display form, `customfield_19880148*3` becomes `{故事点}*3` when
+/// `names` maps `customfield_19880148` to `故事点`. Display names are always braced.
+pub fn ids_to_names(source: &str, names: &HashMap<String, String>) -> Result<String, RenameError> {
+    rewrite_fields(source, names, |name| format!("{{{}}}", name))
+}
+
+/// Display form back to id form, `names` maps ids to display names as for [`ids_to_names`].
+pub fn names_to_ids(source: &str, names: &HashMap<String, String>) -> Result<String, RenameError> {
+    let mut ids = HashMap::new();
+    for (id, name) in names {
+        if ids.insert(name.clone(), id.clone()).is_some() {
+            return Err(RenameError::DuplicateName(name.clone()));
+        }
+    }
+    rename_fields(source, &ids)
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use crate::{
+    fold_children, Ast, AstKind, BinaryOperator, EvalOptions, ExpValue, Fold, FunctionPart,
+    NullMode, ValueType,
+};
+
+/// Values the grammar can write, so an optimized tree still formats to parseable source.
+fn has_literal_syntax(value: &ExpValue) -> bool {
+    match value {
+        ExpValue::Number(n) => n.is_finite(),
+        ExpValue::Integer(_) | ExpValue::Date(_) => true,
+        ExpValue::Duration(millis) => *millis >= 0,
+        ExpValue::Array(items) => items.iter().all(has_literal_syntax),
+        _ => false,
+    }
+}
+
+fn literal_number(ast: &Ast) -> Option<f64> {
+    match &ast.kind {
+        AstKind::Literal(value @ (ExpValue::Integer(_) | ExpValue::Number(_))) => value.as_number(),
+        _ => None,
+    }
+}
+
+/// Integer literal of magnitude 2^k. Multiplying by it is exact, so `x * c1 * c2`
+/// equals `x * (c1 * c2)` when `c1` or `c2` is one.
+fn power_of_two(ast: &Ast) -> bool {
+    matches!(ast.kind, AstKind::Literal(ExpValue::Integer(n)) if n.unsigned_abs().is_power_of_two())
+}
+
+fn integer_literal(ast: &Ast) -> Option<i64> {
+    match ast.kind {
+        AstKind::Literal(ExpValue::Integer(n)) => Some(n),
+        _ => None,
+    }
+}
+
+struct Optimizer<'a> {
+    types: &'a HashMap<String, ValueType>,
+    options: &'a EvalOptions,
+}
+
+impl Optimizer<'_> {
+    fn is_number(&self, ast: &Ast) -> bool {
+        ast.infer_type(self.types) == Ok(ValueType::Number)
+    }
+
+    /// Whether `x + 0` may be replaced by `x`: a null `x` would become 0 under
+    /// [`NullMode::Zero`] and decimal results are rounded.
+    fn identities(&self) -> bool {
+        #[cfg(feature = "decimal")]
+        if self.options.decimal.is_some() {
+            return false;
+        }
+        self.options.null_mode == NullMode::Propagate
+    }
+
+    /// The node evaluated, when all of its operands are literals.
+    fn constant(&self, ast: &Ast) -> Option<Ast> {
+        let literal = |ast: &Ast| matches!(ast.kind, AstKind::Literal(_));
+        let constant = match &ast.kind {
+            AstKind::Literal(_) | AstKind::Identifier(_) => false,
+            AstKind::Function(func) => func.parts.iter().all(|part| match part {
+                FunctionPart::Expression(ast) => literal(ast),
+                _ => false,
+            }),
+            AstKind::Binary(_, lhs, rhs) | AstKind::Index(lhs, rhs) => literal(lhs) && literal(rhs),
+            AstKind::Array(items) => items.iter().all(literal),
+            AstKind::Member(target, _, _) => literal(target),
+        };
+        if !constant {
+            return None;
+        }
+        let value = ast.eval_with(&HashMap::new(), self.options);
+        match has_literal_syntax(&value) {
+            true => Some(Ast {
+                kind: AstKind::Literal(value),
+                span: ast.span,
+            }),
+            false => None,
+        }
+    }
+
+    fn simplify(&self, op: BinaryOperator, lhs: Ast, rhs: Ast) -> AstKind {
+        let number = |ast: &Ast| self.is_number(ast);
+        let is = |ast: &Ast, n: f64| literal_number(ast) == Some(n);
+        match op {
+            BinaryOperator::Add if self.identities() && is(&rhs, 0.0) && number(&lhs) => {
+                return lhs.kind
+            }
+            BinaryOperator::Add if self.identities() && is(&lhs, 0.0) && number(&rhs) => {
+                return rhs.kind
+            }
+            BinaryOperator::Subtract if self.identities() && is(&rhs, 0.0) && number(&lhs) => {
+                return lhs.kind
+            }
+            // not `x / 1`, which turns an integer `x` into a float
+            BinaryOperator::Multiply if self.identities() && is(&rhs, 1.0) && number(&lhs) => {
+                return lhs.kind
+            }
+            BinaryOperator::Multiply if self.identities() && is(&lhs, 1.0) && number(&rhs) => {
+                return rhs.kind
+            }
+            // `x - x` is `x * 0`: zero for numbers, still null for null and an error for errors.
+            BinaryOperator::Subtract
+                if lhs.without_spans() == rhs.without_spans() && number(&lhs) =>
+            {
+                let span = rhs.span;
+                return AstKind::Binary(
+                    BinaryOperator::Multiply,
+                    Box::new(lhs),
+                    Box::new(Ast {
+                        kind: AstKind::Literal(ExpValue::Integer(0)),
+                        span,
+                    }),
+                );
+            }
+            _ => {}
+        }
+        if op == BinaryOperator::Multiply {
+            if let Some(kind) = self.reassociate(&lhs, &rhs) {
+                return kind;
+            }
+        }
+        AstKind::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    /// `x * c1 * c2` as `x * (c1 * c2)` for integer constants, see [`power_of_two`]. A zero
+    /// product is kept apart, `x * 2` may overflow to infinity and infinity times 0 is NaN.
+    fn reassociate(&self, lhs: &Ast, rhs: &Ast) -> Option<AstKind> {
+        let (inner, c2) = match (&lhs.kind, integer_literal(rhs)) {
+            (AstKind::Binary(BinaryOperator::Multiply, a, b), Some(_)) => ((a, b), rhs),
+            _ => match (integer_literal(lhs), &rhs.kind) {
+                (Some(_), AstKind::Binary(BinaryOperator::Multiply, a, b)) => ((a, b), lhs),
+                _ => return None,
+            },
+        };
+        let (x, c1) = match (integer_literal(inner.0), integer_literal(inner.1)) {
+            (None, Some(_)) => (inner.0, inner.1),
+            (Some(_), None) => (inner.1, inner.0),
+            _ => return None,
+        };
+        if !(power_of_two(c1) || power_of_two(c2)) || !self.is_number(x) {
+            return None;
+        }
+        let product = integer_literal(c1)?
+            .checked_mul(integer_literal(c2)?)
+            .filter(|product| *product != 0)?;
+        Some(AstKind::Binary(
+            BinaryOperator::Multiply,
+            x.clone(),
+            Box::new(Ast {
+                kind: AstKind::Literal(ExpValue::Integer(product)),
+                span: c1.span.merge(c2.span),
+            }),
+        ))
+    }
+}
+
+impl Fold for Optimizer<'_> {
+    fn fold_ast(&mut self, ast: Ast) -> Ast {
+        let ast = fold_children(self, ast);
+        if let Some(constant) = self.constant(&ast) {
+            return constant;
+        }
+        match ast.kind {
+            AstKind::Binary(op, lhs, rhs) => Ast {
+                kind: self.simplify(op, *lhs, *rhs),
+                span: ast.span,
+            },
+            kind => Ast {
+                kind,
+                span: ast.span,
+            },
+        }
+    }
+}
+
+impl Ast {
+    /// Folds constants and removes identities, see [`Ast::optimize_with`].
+    pub fn optimize(&self, types: &HashMap<String, ValueType>) -> Ast {
+        self.optimize_with(types, &EvalOptions::default())
+    }
+
+    /// Simplified tree that evaluates to the same value as this one under `options`.
+    /// Operations on literals are folded, `x + 0`, `x - 0` and `x * 1` become `x`
+    /// and `x - x` becomes `x * 0` when `types` says `x` is a number, and integer factors
+    /// are combined, `estimatePoint * 3 * 2` becomes `estimatePoint * 6`.
+    pub fn optimize_with(&self, types: &HashMap<String, ValueType>, options: &EvalOptions) -> Ast {
+        self.clone().fold(&mut Optimizer { types, options })
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(feature = "decimal")]
+use crate::DecimalOptions;
+use crate::{Coercion, ExpValue, ValueError, DEFAULT_EPSILON};
+
+/// What `x / 0`, `x // 0` and `x % 0` evaluate to.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum DivisionByZero {
+    /// Floating point semantics, `1 / 0` is infinity and `0 / 0` is NaN.
+    Ieee,
+    /// `ExpValue::Error(ValueError::DivByZero)`.
+    #[default]
+    Error,
+    Null,
+    Value(ExpValue),
+}
+
+/// How operators treat `Null` operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullMode {
+    /// `null + 1` is `null`.
+    #[default]
+    Propagate,
+    /// `null + 1` is `1`, as in spreadsheets. A null next to a date or duration is a zero duration.
+    Zero,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalOptions {
+    /// Evaluate numbers as exact decimals, rounded after every operation.
+    #[cfg(feature = "decimal")]
+    pub decimal: Option<DecimalOptions>,
+    pub division_by_zero: DivisionByZero,
+    /// Let `NaN` reach the caller. When false every NaN becomes `ValueError::NaN`.
+    pub allow_nan: bool,
+    pub null_mode: NullMode,
+    pub coercion: Coercion,
+    /// Relative tolerance of numeric comparisons, `0.1 + 0.2 = 0.3` holds with the default.
+    pub epsilon: f64,
+}
+
+impl Default for EvalOptions {
+    fn default() -> Self {
+        EvalOptions {
+            #[cfg(feature = "decimal")]
+            decimal: None,
+            division_by_zero: DivisionByZero::default(),
+            allow_nan: false,
+            null_mode: NullMode::default(),
+            coercion: Coercion::default(),
+            epsilon: DEFAULT_EPSILON,
+        }
+    }
+}
+
+impl EvalOptions {
+    /// Replaces a `Null` operand according to [`NullMode`], `other` being the opposite operand.
+    pub fn null_operand(&self, value: ExpValue, other: &ExpValue) -> ExpValue {
+        match (self.null_mode, value) {
+            (NullMode::Zero, ExpValue::Null) => match other {
+                ExpValue::Date(_) | ExpValue::Duration(_) => ExpValue::Duration(0),
+                _ => ExpValue::Integer(0),
+            },
+            (_, value) => value,
+        }
+    }
+
+    pub fn check_nan(&self, value: ExpValue) -> ExpValue {
+        match value {
+            ExpValue::Number(n) if n.is_nan() && !self.allow_nan => {
+                ExpValue::Error(ValueError::NaN)
+            }
+            ExpValue::Array(items) if !self.allow_nan => {
+                ExpValue::Array(items.into_iter().map(|v| self.check_nan(v)).collect())
+            }
+            ExpValue::Object(fields) if !self.allow_nan => ExpValue::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, v)| (name, self.check_nan(v)))
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::cmp::Ordering;
+
+#[cfg(feature = "decimal")]
+use crate::calc::decimal::decimal_operands;
+use crate::{calc::calc::Rule, CompareOperator, ExpValue, ValueError};
+
+/// Relative tolerance of numeric equality unless [`crate::EvalOptions::epsilon`] says otherwise.
+pub const DEFAULT_EPSILON: f64 = 1e-9;
+
+/// Whether `a` and `b` differ by at most `epsilon` relative to the larger magnitude,
+/// or absolutely below 1. With an epsilon of 0 this is `a == b`.
+pub fn approx_eq(a: f64, b: f64, epsilon: f64) -> bool {
+    a == b || (a - b).abs() <= epsilon * a.abs().max(b.abs()).max(1.0)
+}
+
+impl ExpValue {
+    fn type_rank(&self) -> u8 {
+        match self {
+            ExpValue::Null => 0,
+            ExpValue::Bool(_) => 1,
+            ExpValue::Number(_) | ExpValue::Integer(_) => 2,
+            #[cfg(feature = "decimal")]
+            ExpValue::Decimal(_) => 2,
+            ExpValue::String(_) => 3,
+            ExpValue::Date(_) => 4,
+            ExpValue::Duration(_) => 5,
+            ExpValue::Array(_) => 6,
+            ExpValue::Object(_) => 7,
+            ExpValue::Error(_) => 8,
+        }
+    }
+
+    /// A total order over all values, for sorting: null, booleans, numbers, strings,
+    /// dates, durations, arrays, objects and errors, each compared by value.
+    /// NaN sorts above every other number.
+    pub fn total_cmp(&self, other: &ExpValue) -> Ordering {
+        match (self, other) {
+            (ExpValue::Bool(a), ExpValue::Bool(b)) => a.cmp(b),
+            (ExpValue::Integer(a), ExpValue::Integer(b)) => a.cmp(b),
+            (ExpValue::String(a), ExpValue::String(b)) => a.cmp(b),
+            (ExpValue::Date(a), ExpValue::Date(b)) => a.cmp(b),
+            (ExpValue::Duration(a), ExpValue::Duration(b)) => a.cmp(b),
+            (ExpValue::Error(a), ExpValue::Error(b)) => a.cmp(b),
+            (ExpValue::Array(a), ExpValue::Array(b)) => a
+                .iter()
+                .zip(b.iter())
+                .map(|(a, b)| a.total_cmp(b))
+                .find(|o| o.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (ExpValue::Object(a), ExpValue::Object(b)) => a
+                .iter()
+                .zip(b.iter())
+                .map(|((ka, va), (kb, vb))| ka.cmp(kb).then_with(|| va.total_cmp(vb)))
+                .find(|o| o.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (a, b) if a.type_rank() == 2 && b.type_rank() == 2 => {
+                #[cfg(feature = "decimal")]
+                if let Some((a, b)) = decimal_operands(a, b) {
+                    return a.cmp(&b);
+                }
+                let (a, b) = (a.as_number().unwrap_or(0.0), b.as_number().unwrap_or(0.0));
+                a.total_cmp(&b)
+            }
+            (a, b) => a.type_rank().cmp(&b.type_rank()),
+        }
+    }
+
+    /// Ordering for comparison operators. Numbers within `epsilon` of each other are
+    /// equal, values of different types and NaN are not comparable.
+    pub fn compare(&self, other: &ExpValue, epsilon: f64) -> Option<Ordering> {
+        match (self, other) {
+            (ExpValue::Number(_), _) | (_, ExpValue::Number(_))
+                if self.type_rank() == 2 && other.type_rank() == 2 =>
+            {
+                let (a, b) = (self.as_number()?, other.as_number()?);
+                if approx_eq(a, b, epsilon) {
+                    Some(Ordering::Equal)
+                } else {
+                    a.partial_cmp(&b)
+                }
+            }
+            (ExpValue::Array(_) | ExpValue::Object(_) | ExpValue::Error(_), _) => None,
+            (a, b) if a.type_rank() == b.type_rank() && a.type_rank() > 0 => Some(a.total_cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+impl CompareOperator {
+    pub fn from(rule: Rule) -> Option<CompareOperator> {
+        match rule {
+            Rule::compare_eq => Some(CompareOperator::Equal),
+            Rule::compare_ne => Some(CompareOperator::NotEqual),
+            Rule::compare_gt => Some(CompareOperator::GreaterThan),
+            Rule::compare_ge => Some(CompareOperator::GreaterThanOrEqual),
+            Rule::compare_lt => Some(CompareOperator::LessThan),
+            Rule::compare_le => Some(CompareOperator::LessThanOrEqual),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            CompareOperator::Equal => "=",
+            CompareOperator::NotEqual => "!=",
+            CompareOperator::GreaterThan => ">",
+            CompareOperator::GreaterThanOrEqual => ">=",
+            CompareOperator::LessThan => "<",
+            CompareOperator::LessThanOrEqual => "<=",
+        }
+    }
+
+    /// `Bool` result of `lhs op rhs`. Comparing with null gives null, values of different
+    /// types are unequal and have no order.
+    pub fn apply(&self, lhs: &ExpValue, rhs: &ExpValue, epsilon: f64) -> ExpValue {
+        match (lhs, rhs) {
+            (ExpValue::Error(e), _) | (_, ExpValue::Error(e)) => return ExpValue::Error(*e),
+            (ExpValue::Null, _) | (_, ExpValue::Null) => return ExpValue::Null,
+            _ => {}
+        }
+        match (self, lhs.compare(rhs, epsilon)) {
+            (CompareOperator::Equal, ordering) => ExpValue::Bool(ordering == Some(Ordering::Equal)),
+            (CompareOperator::NotEqual, ordering) => {
+                ExpValue::Bool(ordering != Some(Ordering::Equal))
+            }
+            (_, None) => ExpValue::Error(ValueError::TypeMismatch),
+            (CompareOperator::GreaterThan, Some(o)) => ExpValue::Bool(o.is_gt()),
+            (CompareOperator::GreaterThanOrEqual, Some(o)) => ExpValue::Bool(o.is_ge()),
+            (CompareOperator::LessThan, Some(o)) => ExpValue::Bool(o.is_lt()),
+            (CompareOperator::LessThanOrEqual, Some(o)) => ExpValue::Bool(o.is_le()),
+        }
+    }
+
+    /// Whether `lhs op rhs` holds, as used by `field=value` filters.
+    pub fn test(&self, lhs: &ExpValue, rhs: &ExpValue, epsilon: f64) -> bool {
+        self.apply(lhs, rhs, epsilon) == ExpValue::Bool(true)
+    }
+}
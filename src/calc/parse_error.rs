@@ -0,0 +1,126 @@
+use std::fmt;
+
+use pest::error::{Error, ErrorVariant, InputLocation};
+
+use crate::{calc::calc::Rule, BinaryOperator, Locale};
+
+/// Something the parser would have accepted at the position of a [`ParseError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expected {
+    /// A closing bracket, `)` or `]`.
+    Close(char),
+    Value,
+    Field,
+    Number,
+    Date,
+    Duration,
+    List,
+    Operator,
+    /// `.`, `?.` or `[` after a value.
+    Accessor,
+    End,
+}
+
+impl Expected {
+    fn from(rule: Rule) -> Option<Expected> {
+        match rule {
+            Rule::expr | Rule::function_parameter | Rule::function_parameter_item => {
+                Some(Expected::Value)
+            }
+            Rule::ident
+            | Rule::reference
+            | Rule::reference_name
+            | Rule::postfix
+            | Rule::function_parameter_ident
+            | Rule::function_name => Some(Expected::Field),
+            Rule::num | Rule::int => Some(Expected::Number),
+            Rule::date => Some(Expected::Date),
+            Rule::duration => Some(Expected::Duration),
+            Rule::array => Some(Expected::List),
+            Rule::index | Rule::member | Rule::optional_member => Some(Expected::Accessor),
+            Rule::EOI => Some(Expected::End),
+            rule if BinaryOperator::from(rule).is_some() => Some(Expected::Operator),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message(Locale::En))
+    }
+}
+
+/// Why and where a formula failed to parse. `offset` counts bytes and `char_offset`
+/// characters from the start of the formula, `line` and `column` start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseError {
+    pub offset: usize,
+    pub char_offset: usize,
+    pub line: usize,
+    pub column: usize,
+    pub expected: Vec<Expected>,
+    /// The character at `offset`, `None` at the end of the formula.
+    pub found: Option<char>,
+}
+
+impl ParseError {
+    pub fn from(error: Error<Rule>, input: &str) -> ParseError {
+        let offset = match error.location {
+            InputLocation::Pos(offset) => offset,
+            InputLocation::Span((start, _)) => start,
+        };
+        let before = &input[..offset];
+        let mut expected = match error.variant {
+            ErrorVariant::ParsingError { positives, .. } => positives
+                .into_iter()
+                .filter_map(Expected::from)
+                .collect::<Vec<Expected>>(),
+            ErrorVariant::CustomError { .. } => vec![],
+        };
+        // Literal brackets are not rules, so pest does not report them.
+        let can_close = expected
+            .iter()
+            .any(|e| matches!(e, Expected::Operator | Expected::Value));
+        if let Some(close) = unclosed(before).filter(|_| can_close) {
+            expected.push(Expected::Close(close));
+        }
+        expected.sort();
+        expected.dedup();
+        ParseError {
+            offset,
+            char_offset: before.chars().count(),
+            line: before.matches('\n').count() + 1,
+            column: before.chars().rev().take_while(|c| *c != '\n').count() + 1,
+            expected,
+            found: input[offset..].chars().next(),
+        }
+    }
+}
+
+/// Closing bracket of the innermost bracket still open at the end of `text`.
+fn unclosed(text: &str) -> Option<char> {
+    let mut open = vec![];
+    for c in text.chars() {
+        match c {
+            '(' => open.push(')'),
+            '[' => open.push(']'),
+            ')' | ']' => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    open.pop()
+}
+
+/// Reads as: expected a number or a field name at 1:32, found `)`.
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message(Locale::En))
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,237 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    calc::format::path_source, Ast, AstKind, FieldType, Function, FunctionPart, Locale, Span,
+    TypeErrorKind, ValueType,
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    fields: HashMap<String, ValueType>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    UnknownIdentifier(String),
+    UnknownField(String),
+    NotACollection(String, ValueType),
+    /// Path, expected type, found type.
+    IncompatibleField(String, ValueType, ValueType),
+    /// Path, type of the filtered field, filter value.
+    InvalidFilterValue(String, ValueType, String),
+    Type(TypeErrorKind),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub span: Span,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message(Locale::En))
+    }
+}
+
+impl Schema {
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    pub fn field(mut self, name: &str, value_type: ValueType) -> Schema {
+        self.insert(name, value_type);
+        self
+    }
+
+    pub fn insert(&mut self, name: &str, value_type: ValueType) {
+        self.fields.insert(name.to_string(), value_type);
+    }
+
+    pub fn types(&self) -> &HashMap<String, ValueType> {
+        &self.fields
+    }
+
+    pub fn get(&self, name: &str) -> Option<ValueType> {
+        self.fields
+            .get(name)
+            .cloned()
+            .or_else(|| ValueType::builtin(name))
+    }
+
+    /// Resolves a dotted path such as `subtask.estimatePoint`.
+    pub fn resolve(&self, path: &str) -> Option<ValueType> {
+        let mut segments = path.split('.');
+        let mut value_type = self.get(segments.next()?)?;
+        for segment in segments {
+            value_type = value_type.field(segment)?;
+        }
+        Some(value_type)
+    }
+
+    /// Checks that every identifier and `collection.field` path exists and is used
+    /// with a compatible type. Diagnostics are ordered by position.
+    pub fn validate(&self, ast: &Ast) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        self.validate_node(ast, &mut diagnostics);
+        if let Err(err) = ast.infer_type(&self.fields) {
+            diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::Type(err.kind),
+                span: err.span,
+            });
+        }
+        diagnostics.sort_by_key(|d| (d.span.start, d.span.end));
+        diagnostics
+    }
+
+    /// [`Schema::validate`] plus a check of the result against the declared field type.
+    pub fn check(&self, ast: &Ast, field_type: FieldType) -> Vec<Diagnostic> {
+        let mut diagnostics = self.validate(ast);
+        if diagnostics.is_empty() {
+            if let Err(err) = ast.check_field_type(field_type, &self.fields) {
+                diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::Type(err.kind),
+                    span: err.span,
+                });
+            }
+        }
+        diagnostics
+    }
+
+    fn validate_node(&self, ast: &Ast, diagnostics: &mut Vec<Diagnostic>) {
+        match &ast.kind {
+            AstKind::Literal(_) => {}
+            AstKind::Identifier(name) => {
+                if self.get(name).is_none() {
+                    diagnostics.push(Diagnostic {
+                        kind: DiagnosticKind::UnknownIdentifier(name.clone()),
+                        span: ast.span,
+                    });
+                }
+            }
+            AstKind::Member(target, name, _) => {
+                let errors = diagnostics.len();
+                self.validate_node(target, diagnostics);
+                if diagnostics.len() > errors {
+                    return;
+                }
+                let known = match target.infer_type(&self.fields) {
+                    Ok(target_type) => target_type.field(name).is_some(),
+                    Err(_) => true,
+                };
+                if !known {
+                    diagnostics.push(Diagnostic {
+                        kind: DiagnosticKind::UnknownField(ast.path().unwrap_or(name.clone())),
+                        span: ast.span,
+                    });
+                }
+            }
+            AstKind::Function(func) => self.validate_function(func, diagnostics),
+            AstKind::Array(items) => {
+                for item in items {
+                    self.validate_node(item, diagnostics);
+                }
+            }
+            AstKind::Binary(_, lhs, rhs) | AstKind::Index(lhs, rhs) => {
+                self.validate_node(lhs, diagnostics);
+                self.validate_node(rhs, diagnostics);
+            }
+        }
+    }
+
+    fn validate_function(&self, func: &Function, diagnostics: &mut Vec<Diagnostic>) {
+        let mut collection: Option<(String, ValueType)> = None;
+
+        for (part, span) in func.parts.iter().zip(func.part_spans.iter()) {
+            let mut report = |kind| {
+                diagnostics.push(Diagnostic { kind, span: *span });
+            };
+
+            match part {
+                FunctionPart::Identifier(root) | FunctionPart::IdentifierFWithField(root, _) => {
+                    let names = match part {
+                        FunctionPart::IdentifierFWithField(_, names) => names.as_slice(),
+                        _ => &[],
+                    };
+                    let path = path_source(&[std::slice::from_ref(root), names].concat());
+                    let root_type = match self.get(root) {
+                        Some(root_type) => root_type,
+                        None => {
+                            report(DiagnosticKind::UnknownIdentifier(root.clone()));
+                            continue;
+                        }
+                    };
+                    let value_type = match names
+                        .iter()
+                        .try_fold(root_type.clone(), |t, name| t.field(name))
+                    {
+                        Some(value_type) => value_type,
+                        None => {
+                            report(DiagnosticKind::UnknownField(path));
+                            continue;
+                        }
+                    };
+
+                    let sum_type = ValueType::Array(Box::new(ValueType::Number));
+                    let problem = match (func.name.to_lowercase().as_str(), &value_type) {
+                        ("count", ValueType::Array(_) | ValueType::Any) => None,
+                        ("count", _) => {
+                            Some(DiagnosticKind::NotACollection(path, value_type.clone()))
+                        }
+                        ("sum", ValueType::Any) => None,
+                        ("sum", ValueType::Array(item))
+                            if matches!(**item, ValueType::Number | ValueType::Any) =>
+                        {
+                            None
+                        }
+                        ("sum", _) => Some(DiagnosticKind::IncompatibleField(
+                            path,
+                            sum_type,
+                            value_type.clone(),
+                        )),
+                        _ => None,
+                    };
+                    if let Some(problem) = problem {
+                        report(problem);
+                    }
+                    collection = Some((root.clone(), root_type));
+                }
+                FunctionPart::Expression(ast) => self.validate_node(ast, diagnostics),
+                FunctionPart::CompareExpression(field, _, value) => {
+                    let (root, root_type) = match &collection {
+                        Some(collection) => collection,
+                        None => continue,
+                    };
+                    let path =
+                        path_source(&[std::slice::from_ref(root), field.as_slice()].concat());
+                    let field_type = match root_type {
+                        ValueType::Array(item) => field
+                            .iter()
+                            .try_fold(*item.clone(), |t, name| t.field(name)),
+                        ValueType::Any => Some(ValueType::Any),
+                        _ => None,
+                    };
+                    let field_type = match field_type {
+                        Some(field_type) => field_type,
+                        None => {
+                            report(DiagnosticKind::UnknownField(path));
+                            continue;
+                        }
+                    };
+                    let value_type = if value.parse::<f64>().is_ok() {
+                        ValueType::Number
+                    } else {
+                        ValueType::String
+                    };
+                    if field_type != ValueType::Any && field_type != value_type {
+                        report(DiagnosticKind::InvalidFilterValue(
+                            path,
+                            field_type,
+                            value.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
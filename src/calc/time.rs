@@ -0,0 +1,199 @@
+pub const MILLIS_PER_SECOND: i64 = 1000;
+pub const MILLIS_PER_MINUTE: i64 = 60 * MILLIS_PER_SECOND;
+pub const MILLIS_PER_HOUR: i64 = 60 * MILLIS_PER_MINUTE;
+pub const MILLIS_PER_DAY: i64 = 24 * MILLIS_PER_HOUR;
+pub const MILLIS_PER_WEEK: i64 = 7 * MILLIS_PER_DAY;
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Inverse of [`days_from_civil`], returns `(year, month, day)`.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        _ if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        _ => 28,
+    }
+}
+
+fn parse_date(input: &str) -> Option<i64> {
+    let mut parts = input.splitn(3, '-');
+    let year = parts.next()?.parse::<i64>().ok()?;
+    let month = parts.next()?.parse::<u32>().ok()?;
+    let day = parts.next()?.parse::<u32>().ok()?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day) * MILLIS_PER_DAY)
+}
+
+fn parse_time(input: &str) -> Option<i64> {
+    let (time, fraction) = match input.split_once('.') {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (input, None),
+    };
+    let mut parts = time.split(':');
+    let hour = parts.next()?.parse::<i64>().ok()?;
+    let minute = parts.next()?.parse::<i64>().ok()?;
+    let second = parts
+        .next()
+        .map(|s| s.parse::<i64>().ok())
+        .unwrap_or(Some(0))?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let millis = match fraction {
+        Some(fraction) if !fraction.is_empty() && fraction.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("{:0<3}", &fraction[..fraction.len().min(3)])
+                .parse::<i64>()
+                .ok()?
+        }
+        Some(_) => return None,
+        None => 0,
+    };
+    Some(hour * MILLIS_PER_HOUR + minute * MILLIS_PER_MINUTE + second * MILLIS_PER_SECOND + millis)
+}
+
+fn parse_offset(input: &str) -> Option<i64> {
+    if input.is_empty() || input == "Z" {
+        return Some(0);
+    }
+    let (sign, digits) = match (input.strip_prefix('+'), input.strip_prefix('-')) {
+        (Some(digits), _) => (1, digits),
+        (_, Some(digits)) => (-1, digits),
+        _ => return None,
+    };
+    let digits = digits.replace(':', "");
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hour = digits[..2].parse::<i64>().ok()?;
+    let minute = digits[2..].parse::<i64>().ok()?;
+    Some(sign * (hour * MILLIS_PER_HOUR + minute * MILLIS_PER_MINUTE))
+}
+
+/// Parses `2024-03-01`, `2024-03-01T10:00:00Z` or `#2024-03-01#` into
+/// milliseconds since the Unix epoch (UTC). Times without an offset are UTC.
+pub fn parse_date_literal(input: &str) -> Option<i64> {
+    let input = input.trim().trim_matches('#');
+    let (date, rest) = match input.split_once('T') {
+        Some((date, rest)) => (date, Some(rest)),
+        None => (input, None),
+    };
+    let date = parse_date(date)?;
+    match rest {
+        Some(rest) => {
+            let offset_at = rest.find(['Z', '+', '-']);
+            let (time, offset) = match offset_at {
+                Some(i) => rest.split_at(i),
+                None => (rest, ""),
+            };
+            Some(date + parse_time(time)? - parse_offset(offset)?)
+        }
+        None => Some(date),
+    }
+}
+
+/// Formats milliseconds since the Unix epoch as `2024-03-01T10:00:00Z`, the milliseconds
+/// are only written when not zero. [`parse_date_literal`] reads the result back.
+pub fn format_date_literal(millis: i64) -> String {
+    let (year, month, day) = civil_from_days(millis.div_euclid(MILLIS_PER_DAY));
+    let time = millis.rem_euclid(MILLIS_PER_DAY);
+    let text = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / MILLIS_PER_HOUR,
+        time % MILLIS_PER_HOUR / MILLIS_PER_MINUTE,
+        time % MILLIS_PER_MINUTE / MILLIS_PER_SECOND
+    );
+    match time % MILLIS_PER_SECOND {
+        0 => format!("{}Z", text),
+        ms => format!("{}.{:03}Z", text, ms),
+    }
+}
+
+/// Formats milliseconds as a duration literal such as `3d4h30m`, the inverse of
+/// [`parse_duration_literal`]. Negative durations get a leading `-`.
+pub fn format_duration_literal(millis: i64) -> String {
+    let units = [
+        (MILLIS_PER_DAY, "d"),
+        (MILLIS_PER_HOUR, "h"),
+        (MILLIS_PER_MINUTE, "m"),
+        (MILLIS_PER_SECOND, "s"),
+        (1, "ms"),
+    ];
+    let mut rest = millis.unsigned_abs();
+    let mut text = String::new();
+    for (size, unit) in units {
+        let amount = rest / size as u64;
+        rest %= size as u64;
+        if amount > 0 {
+            text.push_str(&format!("{}{}", amount, unit));
+        }
+    }
+    match (millis, text.is_empty()) {
+        (_, true) => "0s".to_string(),
+        (m, false) if m < 0 => format!("-{}", text),
+        _ => text,
+    }
+}
+
+/// Parses duration literals such as `3d`, `4h30m` or `1w2d` into milliseconds.
+///
+/// Units: `w` weeks, `d` days, `h` hours, `m` minutes, `s` seconds, `ms` milliseconds.
+pub fn parse_duration_literal(input: &str) -> Option<i64> {
+    let mut rest = input.trim();
+    let mut total: i64 = 0;
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return None;
+        }
+        let amount = rest.get(..digits)?.parse::<i64>().ok()?;
+        rest = &rest[digits..];
+        let (unit, len) = if rest.starts_with("ms") {
+            (1, 2)
+        } else {
+            match rest.chars().next()? {
+                'w' => (MILLIS_PER_WEEK, 1),
+                'd' => (MILLIS_PER_DAY, 1),
+                'h' => (MILLIS_PER_HOUR, 1),
+                'm' => (MILLIS_PER_MINUTE, 1),
+                's' => (MILLIS_PER_SECOND, 1),
+                _ => return None,
+            }
+        };
+        rest = &rest[len..];
+        total = total.checked_add(amount.checked_mul(unit)?)?;
+    }
+    Some(total)
+}
@@ -0,0 +1,292 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    Ast, AstKind, BinaryOperator, CompareOperator, ExpValue, FieldType, FunctionPart, Locale, Span,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueType {
+    /// Not known statically, accepted everywhere.
+    Any,
+    Bool,
+    Number,
+    String,
+    Date,
+    Duration,
+    Array(Box<ValueType>),
+    Object(BTreeMap<String, ValueType>),
+}
+
+impl ValueType {
+    pub fn of(value: &ExpValue) -> ValueType {
+        match value {
+            ExpValue::Error(_) | ExpValue::Null => ValueType::Any,
+            ExpValue::Bool(_) => ValueType::Bool,
+            ExpValue::Number(_) | ExpValue::Integer(_) => ValueType::Number,
+            #[cfg(feature = "decimal")]
+            ExpValue::Decimal(_) => ValueType::Number,
+            ExpValue::String(_) => ValueType::String,
+            ExpValue::Date(_) => ValueType::Date,
+            ExpValue::Duration(_) => ValueType::Duration,
+            ExpValue::Array(items) => ValueType::Array(Box::new(
+                items.first().map(ValueType::of).unwrap_or(ValueType::Any),
+            )),
+            ExpValue::Object(fields) => ValueType::Object(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.clone(), ValueType::of(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            ValueType::Any => "Any".to_string(),
+            ValueType::Bool => "Bool".to_string(),
+            ValueType::Number => "Number".to_string(),
+            ValueType::String => "String".to_string(),
+            ValueType::Date => "Date".to_string(),
+            ValueType::Duration => "Duration".to_string(),
+            ValueType::Array(item) => format!("Array<{}>", item.name()),
+            ValueType::Object(fields) => format!(
+                "{{{}}}",
+                fields
+                    .iter()
+                    .map(|(name, value_type)| format!("{}: {}", name, value_type.name()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    /// Parses the notation produced by [`ValueType::name`], e.g.
+    /// `Array<{estimatePoint: Number, status: Number}>`.
+    pub fn parse(input: &str) -> Option<ValueType> {
+        let (value_type, rest) = ValueType::parse_prefix(input)?;
+        if rest.trim().is_empty() {
+            Some(value_type)
+        } else {
+            None
+        }
+    }
+
+    fn parse_prefix(input: &str) -> Option<(ValueType, &str)> {
+        let input = input.trim_start();
+        if let Some(mut rest) = input.strip_prefix('{') {
+            let mut fields = BTreeMap::new();
+            loop {
+                rest = rest.trim_start();
+                if let Some(rest) = rest.strip_prefix('}') {
+                    return Some((ValueType::Object(fields), rest));
+                }
+                let (name, after_name) = rest.split_once(':')?;
+                let (value_type, after_type) = ValueType::parse_prefix(after_name)?;
+                fields.insert(name.trim().to_string(), value_type);
+                rest = after_type.trim_start();
+                rest = rest.strip_prefix(',').unwrap_or(rest);
+            }
+        }
+
+        let end = input
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(input.len());
+        let (name, rest) = input.split_at(end);
+        let value_type = match name.to_lowercase().as_str() {
+            "any" => ValueType::Any,
+            "bool" => ValueType::Bool,
+            "number" => ValueType::Number,
+            "string" => ValueType::String,
+            "date" => ValueType::Date,
+            "duration" => ValueType::Duration,
+            "array" => {
+                let (item, rest) = ValueType::parse_prefix(rest.trim_start().strip_prefix('<')?)?;
+                let rest = rest.trim_start().strip_prefix('>')?;
+                return Some((ValueType::Array(Box::new(item)), rest));
+            }
+            _ => return None,
+        };
+        Some((value_type, rest))
+    }
+
+    /// Type of `self.name`. Fields of an array's elements are lifted into an array,
+    /// so `subtask.estimatePoint` is `Array<Number>` for `subtask: Array<{estimatePoint: Number}>`.
+    pub fn field(&self, name: &str) -> Option<ValueType> {
+        match self {
+            ValueType::Any => Some(ValueType::Any),
+            ValueType::Object(fields) => fields.get(name).cloned(),
+            ValueType::Array(item) => item.field(name).map(|t| match t {
+                ValueType::Any => ValueType::Any,
+                t => ValueType::Array(Box::new(t)),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Type of the host-provided `GET_*` identifiers when the schema does not say otherwise.
+    pub fn builtin(name: &str) -> Option<ValueType> {
+        match name {
+            "GET_NOW" | "GET_TODAY" | "GET_CREATE_TIME" | "GET_UPDATE_TIME" => {
+                Some(ValueType::Date)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn binary(op: BinaryOperator, lhs: &ValueType, rhs: &ValueType) -> Option<ValueType> {
+        use BinaryOperator::*;
+        use ValueType::*;
+
+        match (op, lhs, rhs) {
+            (Compare(_), Any, Any | Bool | Number | String | Date | Duration)
+            | (Compare(_), Bool | Number | String | Date | Duration, Any) => Some(Bool),
+            (_, Any, _) | (_, _, Any) => Some(Any),
+            (_, Array(lhs), Array(rhs)) => {
+                ValueType::binary(op, lhs, rhs).map(|t| Array(Box::new(t)))
+            }
+            (_, Array(lhs), rhs) => ValueType::binary(op, lhs, rhs).map(|t| Array(Box::new(t))),
+            (_, lhs, Array(rhs)) => ValueType::binary(op, lhs, rhs).map(|t| Array(Box::new(t))),
+            (Compare(CompareOperator::Equal | CompareOperator::NotEqual), _, _) => Some(Bool),
+            (Compare(_), lhs, rhs)
+                if lhs == rhs && matches!(lhs, Bool | Number | String | Date | Duration) =>
+            {
+                Some(Bool)
+            }
+            (_, Number, Number) => Some(Number),
+            (Subtract, Date, Date) => Some(Duration),
+            (Add, Date, Duration) | (Add, Duration, Date) | (Subtract, Date, Duration) => {
+                Some(Date)
+            }
+            (Add | Subtract, Duration, Duration) => Some(Duration),
+            (Multiply, Duration, Number) | (Multiply, Number, Duration) => Some(Duration),
+            (Divide, Duration, Number) => Some(Duration),
+            (Divide, Duration, Duration) => Some(Number),
+            _ => None,
+        }
+    }
+}
+
+impl FieldType {
+    pub fn accepts(&self, value_type: &ValueType) -> bool {
+        match self {
+            FieldType::Integer
+            | FieldType::Decimal
+            | FieldType::PercentageNumber
+            | FieldType::PercentageBar => {
+                matches!(value_type, ValueType::Any | ValueType::Number)
+            }
+            FieldType::Date | FieldType::DateTime => matches!(
+                value_type,
+                ValueType::Any | ValueType::Date | ValueType::Duration
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeErrorKind {
+    InvalidOperands(BinaryOperator, ValueType, ValueType),
+    FieldTypeMismatch(FieldType, ValueType),
+    /// Indexed value and index types.
+    InvalidIndex(ValueType, ValueType),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeError {
+    pub kind: TypeErrorKind,
+    pub span: Span,
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message(Locale::En))
+    }
+}
+
+fn identifier_type(name: &str, types: &HashMap<String, ValueType>) -> ValueType {
+    types
+        .get(name)
+        .cloned()
+        .or_else(|| ValueType::builtin(name))
+        .unwrap_or(ValueType::Any)
+}
+
+impl Ast {
+    pub fn infer_type(&self, types: &HashMap<String, ValueType>) -> Result<ValueType, TypeError> {
+        match &self.kind {
+            AstKind::Literal(value) => Ok(ValueType::of(value)),
+            AstKind::Identifier(name) => Ok(identifier_type(name, types)),
+            AstKind::Function(func) => {
+                let mut arg_types = vec![];
+                for part in &func.parts {
+                    arg_types.push(match part {
+                        FunctionPart::Identifier(name) => identifier_type(name, types),
+                        FunctionPart::Expression(ast) => ast.infer_type(types)?,
+                        _ => ValueType::Any,
+                    });
+                }
+                Ok(match func.name.to_lowercase().as_str() {
+                    "sum" | "count" => ValueType::Number,
+                    "isblank" | "iserror" => ValueType::Bool,
+                    "min" | "max" => match arg_types.into_iter().next() {
+                        Some(ValueType::Array(item)) => *item,
+                        Some(value_type) => value_type,
+                        None => ValueType::Any,
+                    },
+                    "coalesce" | "ifnull" => arg_types
+                        .into_iter()
+                        .find(|t| *t != ValueType::Any)
+                        .unwrap_or(ValueType::Any),
+                    _ => ValueType::Any,
+                })
+            }
+            AstKind::Array(items) => {
+                let mut item_type = ValueType::Any;
+                for item in items {
+                    let value_type = item.infer_type(types)?;
+                    if item_type == ValueType::Any {
+                        item_type = value_type;
+                    }
+                }
+                Ok(ValueType::Array(Box::new(item_type)))
+            }
+            AstKind::Member(target, name, _) => Ok(target
+                .infer_type(types)?
+                .field(name)
+                .unwrap_or(ValueType::Any)),
+            AstKind::Index(target, index) => {
+                match (target.infer_type(types)?, index.infer_type(types)?) {
+                    (ValueType::Array(item), ValueType::Number | ValueType::Any) => Ok(*item),
+                    (ValueType::Any, ValueType::Number | ValueType::Any) => Ok(ValueType::Any),
+                    (target, index) => Err(TypeError {
+                        kind: TypeErrorKind::InvalidIndex(target, index),
+                        span: self.span,
+                    }),
+                }
+            }
+            AstKind::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.infer_type(types)?, rhs.infer_type(types)?);
+                ValueType::binary(*op, &lhs, &rhs).ok_or(TypeError {
+                    kind: TypeErrorKind::InvalidOperands(*op, lhs, rhs),
+                    span: self.span,
+                })
+            }
+        }
+    }
+
+    pub fn check_field_type(
+        &self,
+        field_type: FieldType,
+        types: &HashMap<String, ValueType>,
+    ) -> Result<ValueType, TypeError> {
+        let value_type = self.infer_type(types)?;
+        if field_type.accepts(&value_type) {
+            Ok(value_type)
+        } else {
+            Err(TypeError {
+                kind: TypeErrorKind::FieldTypeMismatch(field_type, value_type),
+                span: self.span,
+            })
+        }
+    }
+}
@@ -1,81 +1,460 @@
-use std::ops;
+use std::{collections::BTreeMap, ops};
 
-#[derive(Clone, Debug, PartialEq)]
+#[cfg(feature = "decimal")]
+use crate::calc::decimal::{decimal_operands, decimal_pow, decimal_result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ValueError {
+    /// Operands of incompatible types.
+    TypeMismatch,
+    /// Identifier missing from the evaluation table.
+    UnknownIdentifier,
+    /// A literal such as `#2023-02-29#` that does not denote a value.
+    InvalidLiteral,
+    DivByZero,
+    /// A result that is not a number, e.g. `(-8) ^ 0.5`.
+    NaN,
+    Overflow,
+    /// A construct the evaluator cannot run, such as an unknown function.
+    Unsupported,
+    /// A function called with the wrong number of arguments.
+    InvalidArguments,
+    /// Element-wise operation on arrays of different lengths.
+    LengthMismatch,
+    /// `a.b` where `a` is null, `a?.b` is null instead.
+    NullMember,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExpValue {
-    Error,
+    Error(ValueError),
+    Null,
     Bool(bool),
     Number(f64),
+    Integer(i64),
     String(String),
     Array(Vec<ExpValue>),
+    Object(BTreeMap<String, ExpValue>),
+    /// Milliseconds since the Unix epoch (UTC).
+    Date(i64),
+    /// Milliseconds.
+    Duration(i64),
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
+}
+
+/// Numbers compare by value across representations, so `Integer(3) == Number(3.0)`.
+impl PartialEq for ExpValue {
+    fn eq(&self, other: &ExpValue) -> bool {
+        match (self, other) {
+            (ExpValue::Error(a), ExpValue::Error(b)) => a == b,
+            (ExpValue::Null, ExpValue::Null) => true,
+            (ExpValue::Bool(a), ExpValue::Bool(b)) => a == b,
+            (ExpValue::Number(a), ExpValue::Number(b)) => a == b,
+            (ExpValue::Integer(a), ExpValue::Integer(b)) => a == b,
+            (ExpValue::Integer(a), ExpValue::Number(b))
+            | (ExpValue::Number(b), ExpValue::Integer(a)) => *a as f64 == *b,
+            (ExpValue::String(a), ExpValue::String(b)) => a == b,
+            (ExpValue::Array(a), ExpValue::Array(b)) => a == b,
+            (ExpValue::Object(a), ExpValue::Object(b)) => a == b,
+            (ExpValue::Date(a), ExpValue::Date(b)) => a == b,
+            (ExpValue::Duration(a), ExpValue::Duration(b)) => a == b,
+            #[cfg(feature = "decimal")]
+            (a, b) => match decimal_operands(a, b) {
+                Some((a, b)) => a == b,
+                None => false,
+            },
+            #[cfg(not(feature = "decimal"))]
+            _ => false,
+        }
+    }
+}
+
+/// Applies `op` element-wise when either operand is an array, a scalar is paired with
+/// every element. Returns the operands untouched when neither is an array.
+pub(crate) fn broadcast(
+    lhs: ExpValue,
+    rhs: ExpValue,
+    op: impl Fn(ExpValue, ExpValue) -> ExpValue,
+) -> Result<ExpValue, (ExpValue, ExpValue)> {
+    match (lhs, rhs) {
+        (ExpValue::Array(a), ExpValue::Array(b)) if a.len() != b.len() => {
+            Ok(ExpValue::Error(ValueError::LengthMismatch))
+        }
+        (ExpValue::Array(a), ExpValue::Array(b)) => Ok(ExpValue::Array(
+            a.into_iter().zip(b).map(|(a, b)| op(a, b)).collect(),
+        )),
+        (ExpValue::Array(a), b) if !b.is_error() => Ok(ExpValue::Array(
+            a.into_iter().map(|a| op(a, b.clone())).collect(),
+        )),
+        (a, ExpValue::Array(b)) if !a.is_error() => Ok(ExpValue::Array(
+            b.into_iter().map(|b| op(a.clone(), b)).collect(),
+        )),
+        operands => Err(operands),
+    }
+}
+
+fn floor_div(a: i64, b: i64) -> Option<i64> {
+    let quotient = a.checked_div(b)?;
+    if a % b != 0 && (a < 0) != (b < 0) {
+        Some(quotient - 1)
+    } else {
+        Some(quotient)
+    }
+}
+
+/// A date or duration computed in milliseconds, `Overflow` when it does not fit.
+fn millis_result(millis: Option<i64>, value: fn(i64) -> ExpValue) -> ExpValue {
+    millis
+        .map(value)
+        .unwrap_or(ExpValue::Error(ValueError::Overflow))
+}
+
+/// A duration scaled by a number, `Overflow` when it does not fit and NaN for `0d / 0`.
+fn scaled_duration(millis: f64) -> ExpValue {
+    let millis = millis.round();
+    if millis.is_nan() {
+        ExpValue::Number(f64::NAN)
+    } else if millis >= i64::MIN as f64 && millis < i64::MAX as f64 {
+        ExpValue::Duration(millis as i64)
+    } else {
+        ExpValue::Error(ValueError::Overflow)
+    }
 }
 
 impl ops::Add<ExpValue> for ExpValue {
     type Output = ExpValue;
     fn add(self, _rhs: ExpValue) -> ExpValue {
-        if let ExpValue::Number(a) = self {
-            if let ExpValue::Number(b) = _rhs {
-                return ExpValue::Number(a + b);
+        let (lhs, _rhs) = match broadcast(self, _rhs, |a, b| a + b) {
+            Ok(value) => return value,
+            Err(operands) => operands,
+        };
+        #[cfg(feature = "decimal")]
+        if let Some((a, b)) = decimal_operands(&lhs, &_rhs) {
+            return decimal_result(a.checked_add(b));
+        }
+        match (lhs, _rhs) {
+            (ExpValue::Integer(a), ExpValue::Integer(b)) => a
+                .checked_add(b)
+                .map(ExpValue::Integer)
+                .unwrap_or(ExpValue::Number(a as f64 + b as f64)),
+            (ExpValue::Number(a), ExpValue::Number(b)) => ExpValue::Number(a + b),
+            (ExpValue::Date(a), ExpValue::Duration(b))
+            | (ExpValue::Duration(b), ExpValue::Date(a)) => {
+                millis_result(a.checked_add(b), ExpValue::Date)
+            }
+            (ExpValue::Duration(a), ExpValue::Duration(b)) => {
+                millis_result(a.checked_add(b), ExpValue::Duration)
             }
+            (a, b) if a.is_integer() || b.is_integer() => a.to_float() + b.to_float(),
+            (ExpValue::Error(e), _) | (_, ExpValue::Error(e)) => ExpValue::Error(e),
+            (ExpValue::Null, _) | (_, ExpValue::Null) => ExpValue::Null,
+            _ => ExpValue::Error(ValueError::TypeMismatch),
         }
-        return ExpValue::Error;
     }
 }
 
 impl ops::Sub<ExpValue> for ExpValue {
     type Output = ExpValue;
     fn sub(self, _rhs: ExpValue) -> ExpValue {
-        if let ExpValue::Number(a) = self {
-            if let ExpValue::Number(b) = _rhs {
-                return ExpValue::Number(a - b);
+        let (lhs, _rhs) = match broadcast(self, _rhs, |a, b| a - b) {
+            Ok(value) => return value,
+            Err(operands) => operands,
+        };
+        #[cfg(feature = "decimal")]
+        if let Some((a, b)) = decimal_operands(&lhs, &_rhs) {
+            return decimal_result(a.checked_sub(b));
+        }
+        match (lhs, _rhs) {
+            (ExpValue::Integer(a), ExpValue::Integer(b)) => a
+                .checked_sub(b)
+                .map(ExpValue::Integer)
+                .unwrap_or(ExpValue::Number(a as f64 - b as f64)),
+            (ExpValue::Number(a), ExpValue::Number(b)) => ExpValue::Number(a - b),
+            (ExpValue::Date(a), ExpValue::Date(b)) => {
+                millis_result(a.checked_sub(b), ExpValue::Duration)
+            }
+            (ExpValue::Date(a), ExpValue::Duration(b)) => {
+                millis_result(a.checked_sub(b), ExpValue::Date)
             }
+            (ExpValue::Duration(a), ExpValue::Duration(b)) => {
+                millis_result(a.checked_sub(b), ExpValue::Duration)
+            }
+            (a, b) if a.is_integer() || b.is_integer() => a.to_float() - b.to_float(),
+            (ExpValue::Error(e), _) | (_, ExpValue::Error(e)) => ExpValue::Error(e),
+            (ExpValue::Null, _) | (_, ExpValue::Null) => ExpValue::Null,
+            _ => ExpValue::Error(ValueError::TypeMismatch),
         }
-        return ExpValue::Error;
     }
 }
 
 impl ops::Mul<ExpValue> for ExpValue {
     type Output = ExpValue;
     fn mul(self, _rhs: ExpValue) -> ExpValue {
-        if let ExpValue::Number(a) = self {
-            if let ExpValue::Number(b) = _rhs {
-                return ExpValue::Number(a * b);
-            }
+        let (lhs, _rhs) = match broadcast(self, _rhs, |a, b| a * b) {
+            Ok(value) => return value,
+            Err(operands) => operands,
+        };
+        #[cfg(feature = "decimal")]
+        if let Some((a, b)) = decimal_operands(&lhs, &_rhs) {
+            return decimal_result(a.checked_mul(b));
+        }
+        match (lhs, _rhs) {
+            (ExpValue::Integer(a), ExpValue::Integer(b)) => a
+                .checked_mul(b)
+                .map(ExpValue::Integer)
+                .unwrap_or(ExpValue::Number(a as f64 * b as f64)),
+            (ExpValue::Number(a), ExpValue::Number(b)) => ExpValue::Number(a * b),
+            (ExpValue::Duration(a), ExpValue::Number(b))
+            | (ExpValue::Number(b), ExpValue::Duration(a)) => scaled_duration(a as f64 * b),
+            (a, b) if a.is_integer() || b.is_integer() => a.to_float() * b.to_float(),
+            (ExpValue::Error(e), _) | (_, ExpValue::Error(e)) => ExpValue::Error(e),
+            (ExpValue::Null, _) | (_, ExpValue::Null) => ExpValue::Null,
+            _ => ExpValue::Error(ValueError::TypeMismatch),
         }
-        return ExpValue::Error;
     }
 }
 
 impl ops::Div<ExpValue> for ExpValue {
     type Output = ExpValue;
     fn div(self, _rhs: ExpValue) -> ExpValue {
-        if let ExpValue::Number(a) = self {
-            if let ExpValue::Number(b) = _rhs {
-                return ExpValue::Number(a / b);
+        let (lhs, _rhs) = match broadcast(self, _rhs, |a, b| a / b) {
+            Ok(value) => return value,
+            Err(operands) => operands,
+        };
+        #[cfg(feature = "decimal")]
+        if let Some((a, b)) = decimal_operands(&lhs, &_rhs) {
+            if b.is_zero() {
+                return ExpValue::Error(ValueError::DivByZero);
             }
+            return decimal_result(a.checked_div(b));
+        }
+        match (lhs, _rhs) {
+            (ExpValue::Number(a), ExpValue::Number(b)) => ExpValue::Number(a / b),
+            (ExpValue::Duration(a), ExpValue::Number(b)) => scaled_duration(a as f64 / b),
+            (ExpValue::Duration(a), ExpValue::Duration(b)) => ExpValue::Number(a as f64 / b as f64),
+            (a, b) if a.is_integer() || b.is_integer() => a.to_float() / b.to_float(),
+            (ExpValue::Error(e), _) | (_, ExpValue::Error(e)) => ExpValue::Error(e),
+            (ExpValue::Null, _) | (_, ExpValue::Null) => ExpValue::Null,
+            _ => ExpValue::Error(ValueError::TypeMismatch),
         }
-        return ExpValue::Error;
     }
 }
 
 impl ExpValue {
+    /// Numeric value as `f64`, for `Number`, `Integer` and `Decimal`.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            ExpValue::Number(n) => Some(*n),
+            ExpValue::Integer(n) => Some(*n as f64),
+            #[cfg(feature = "decimal")]
+            ExpValue::Decimal(d) => rust_decimal::prelude::ToPrimitive::to_f64(d),
+            _ => None,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, ExpValue::Error(_))
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, ExpValue::Null)
+    }
+
+    /// Whether this is a numeric zero, the divisor that triggers the division-by-zero policy.
+    pub fn is_zero(&self) -> bool {
+        match self {
+            ExpValue::Duration(n) => *n == 0,
+            other => other.as_number() == Some(0.0),
+        }
+    }
+
+    /// `self[index]` for an array and an integral index. An index past the end is `Null`.
+    pub fn index(self, index: ExpValue) -> ExpValue {
+        match (self, index) {
+            (ExpValue::Error(e), _) | (_, ExpValue::Error(e)) => ExpValue::Error(e),
+            (ExpValue::Null, _) | (_, ExpValue::Null) => ExpValue::Null,
+            (ExpValue::Array(items), index) => match index.as_number() {
+                Some(n) if n.fract() == 0.0 && n >= 0.0 => {
+                    items.into_iter().nth(n as usize).unwrap_or(ExpValue::Null)
+                }
+                _ => ExpValue::Error(ValueError::TypeMismatch),
+            },
+            _ => ExpValue::Error(ValueError::TypeMismatch),
+        }
+    }
+
+    /// `self.name`, or `self?.name` when `optional`. A missing field is `Null` and the
+    /// field is read from every element of an array.
+    pub fn member(self, name: &str, optional: bool) -> ExpValue {
+        match self {
+            ExpValue::Object(mut fields) => fields.remove(name).unwrap_or(ExpValue::Null),
+            ExpValue::Array(items) => ExpValue::Array(
+                items
+                    .into_iter()
+                    .map(|item| item.member(name, optional))
+                    .collect(),
+            ),
+            ExpValue::Null if optional => ExpValue::Null,
+            ExpValue::Null => ExpValue::Error(ValueError::NullMember),
+            ExpValue::Error(e) => ExpValue::Error(e),
+            _ => ExpValue::Error(ValueError::TypeMismatch),
+        }
+    }
+
+    /// Follows a path such as `["assignee", "name"]` with [`ExpValue::member`].
+    pub fn member_path(self, path: &[String]) -> ExpValue {
+        path.iter()
+            .fold(self, |value, name| value.member(name, false))
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self, ExpValue::Integer(_))
+    }
+
+    /// Turns an `Integer` into a `Number`, other values are returned unchanged.
+    pub fn to_float(self) -> ExpValue {
+        match self {
+            ExpValue::Integer(n) => ExpValue::Number(n as f64),
+            other => other,
+        }
+    }
+
     pub fn powf(self, _rhs: ExpValue) -> ExpValue {
-        if let ExpValue::Number(a) = self {
-            if let ExpValue::Number(b) = _rhs {
-                return ExpValue::Number(a.powf(b));
+        let (lhs, _rhs) = match broadcast(self, _rhs, ExpValue::powf) {
+            Ok(value) => return value,
+            Err(operands) => operands,
+        };
+        #[cfg(feature = "decimal")]
+        if let Some((a, b)) = decimal_operands(&lhs, &_rhs) {
+            return decimal_pow(a, b);
+        }
+        match (lhs, _rhs) {
+            (ExpValue::Integer(a), ExpValue::Integer(b)) if b >= 0 => u32::try_from(b)
+                .ok()
+                .and_then(|b| a.checked_pow(b))
+                .map(ExpValue::Integer)
+                .unwrap_or(ExpValue::Number((a as f64).powf(b as f64))),
+            (ExpValue::Number(a), ExpValue::Number(b)) => ExpValue::Number(a.powf(b)),
+            (a, b) if a.is_integer() || b.is_integer() => a.to_float().powf(b.to_float()),
+            (ExpValue::Error(e), _) | (_, ExpValue::Error(e)) => ExpValue::Error(e),
+            (ExpValue::Null, _) | (_, ExpValue::Null) => ExpValue::Null,
+            _ => ExpValue::Error(ValueError::TypeMismatch),
+        }
+    }
+
+    /// Floor division, the `//` and `div` operators.
+    pub fn int_div(self, _rhs: ExpValue) -> ExpValue {
+        let (lhs, _rhs) = match broadcast(self, _rhs, ExpValue::int_div) {
+            Ok(value) => return value,
+            Err(operands) => operands,
+        };
+        #[cfg(feature = "decimal")]
+        if let Some((a, b)) = decimal_operands(&lhs, &_rhs) {
+            if b.is_zero() {
+                return ExpValue::Error(ValueError::DivByZero);
             }
+            return decimal_result(a.checked_div(b).map(|d| d.floor()));
+        }
+        match (lhs, _rhs) {
+            (ExpValue::Integer(a), ExpValue::Integer(b)) => floor_div(a, b)
+                .map(ExpValue::Integer)
+                .unwrap_or(ExpValue::Number((a as f64 / b as f64).floor())),
+            (ExpValue::Number(a), ExpValue::Number(b)) => ExpValue::Number((a / b).floor()),
+            (ExpValue::Duration(a), ExpValue::Duration(b)) => floor_div(a, b)
+                .map(ExpValue::Integer)
+                .unwrap_or(ExpValue::Error(ValueError::DivByZero)),
+            (a, b) if a.is_integer() || b.is_integer() => a.to_float().int_div(b.to_float()),
+            (ExpValue::Error(e), _) | (_, ExpValue::Error(e)) => ExpValue::Error(e),
+            (ExpValue::Null, _) | (_, ExpValue::Null) => ExpValue::Null,
+            _ => ExpValue::Error(ValueError::TypeMismatch),
         }
-        return ExpValue::Error;
     }
 }
 
 impl ops::Rem<ExpValue> for ExpValue {
     type Output = ExpValue;
     fn rem(self, _rhs: ExpValue) -> ExpValue {
-        if let ExpValue::Number(a) = self {
-            if let ExpValue::Number(b) = _rhs {
-                return ExpValue::Number(a % b);
+        let (lhs, _rhs) = match broadcast(self, _rhs, |a, b| a % b) {
+            Ok(value) => return value,
+            Err(operands) => operands,
+        };
+        #[cfg(feature = "decimal")]
+        if let Some((a, b)) = decimal_operands(&lhs, &_rhs) {
+            if b.is_zero() {
+                return ExpValue::Error(ValueError::DivByZero);
+            }
+            return decimal_result(a.checked_rem(b));
+        }
+        match (lhs, _rhs) {
+            // `i64::MIN % -1` overflows in the division but the remainder is 0
+            (ExpValue::Integer(_), ExpValue::Integer(-1)) => ExpValue::Integer(0),
+            (ExpValue::Integer(a), ExpValue::Integer(b)) => a
+                .checked_rem(b)
+                .map(ExpValue::Integer)
+                .unwrap_or(ExpValue::Number(a as f64 % b as f64)),
+            (ExpValue::Number(a), ExpValue::Number(b)) => ExpValue::Number(a % b),
+            (a, b) if a.is_integer() || b.is_integer() => a.to_float() % b.to_float(),
+            (ExpValue::Error(e), _) | (_, ExpValue::Error(e)) => ExpValue::Error(e),
+            (ExpValue::Null, _) | (_, ExpValue::Null) => ExpValue::Null,
+            _ => ExpValue::Error(ValueError::TypeMismatch),
+        }
+    }
+}
+
+impl ops::Shl<ExpValue> for ExpValue {
+    type Output = ExpValue;
+    fn shl(self, _rhs: ExpValue) -> ExpValue {
+        let (lhs, _rhs) = match broadcast(self, _rhs, |a, b| a << b) {
+            Ok(value) => return value,
+            Err(operands) => operands,
+        };
+        match (lhs, _rhs) {
+            // bits shifted out promote to a number, as `+` does on overflow
+            (ExpValue::Integer(a), ExpValue::Integer(b)) if (0..64).contains(&b) => {
+                match a << b >> b == a {
+                    true => ExpValue::Integer(a << b),
+                    false => ExpValue::Number(a as f64 * 2f64.powi(b as i32)),
+                }
+            }
+            (ExpValue::Number(a), ExpValue::Number(b)) if (0.0..64.0).contains(&b) => {
+                ExpValue::Number(a.trunc() * 2f64.powi(b as i32))
             }
+            (a, b) if a.is_integer() || b.is_integer() => a.to_float() << b.to_float(),
+            (ExpValue::Error(e), _) | (_, ExpValue::Error(e)) => ExpValue::Error(e),
+            (ExpValue::Null, _) | (_, ExpValue::Null) => ExpValue::Null,
+            _ => ExpValue::Error(ValueError::TypeMismatch),
         }
-        return ExpValue::Error;
     }
 }
+
+impl ops::Shr<ExpValue> for ExpValue {
+    type Output = ExpValue;
+    fn shr(self, _rhs: ExpValue) -> ExpValue {
+        let (lhs, _rhs) = match broadcast(self, _rhs, |a, b| a >> b) {
+            Ok(value) => return value,
+            Err(operands) => operands,
+        };
+        match (lhs, _rhs) {
+            (ExpValue::Integer(a), ExpValue::Integer(b)) if (0..64).contains(&b) => {
+                ExpValue::Integer(a >> b)
+            }
+            (ExpValue::Number(a), ExpValue::Number(b)) if (0.0..64.0).contains(&b) => {
+                ExpValue::Number(((a as i64) >> (b as u32)) as f64)
+            }
+            (a, b) if a.is_integer() || b.is_integer() => a.to_float() >> b.to_float(),
+            (ExpValue::Error(e), _) | (_, ExpValue::Error(e)) => ExpValue::Error(e),
+            (ExpValue::Null, _) | (_, ExpValue::Null) => ExpValue::Null,
+            _ => ExpValue::Error(ValueError::TypeMismatch),
+        }
+    }
+}
+
+/// Adds up `values` starting from `Integer(0)`, so a sum of integers stays an integer.
+pub fn sum_values<I: IntoIterator<Item = ExpValue>>(values: I) -> ExpValue {
+    values
+        .into_iter()
+        .fold(ExpValue::Integer(0), |sum, value| sum + value)
+}
@@ -0,0 +1,245 @@
+use crate::{Ast, AstKind, ExpValue, Function, FunctionPart, Span};
+
+/// Read-only traversal of a formula tree. Every method defaults to visiting the children
+/// of the node through the matching `walk_*` function, so an implementation overrides the
+/// nodes it cares about and calls the `walk_*` function itself to keep descending.
+pub trait Visitor {
+    fn visit_ast(&mut self, ast: &Ast) {
+        walk_ast(self, ast)
+    }
+
+    fn visit_literal(&mut self, _value: &ExpValue, _span: Span) {}
+
+    fn visit_identifier(&mut self, _name: &str, _span: Span) {}
+
+    /// Member names, `name` in `parent.name`, `span` is the span of the member expression.
+    /// The sub-fields of a function argument such as `subtask.points` are members too.
+    fn visit_member(&mut self, _name: &str, _span: Span) {}
+
+    /// Field path of a filter argument, `status` in `status=DONE`, read on the filtered items.
+    fn visit_filter_field(&mut self, _path: &[String], _span: Span) {}
+
+    fn visit_function(&mut self, func: &Function, _span: Span) {
+        walk_function(self, func)
+    }
+
+    /// `span` is the span of the whole argument.
+    fn visit_function_part(&mut self, part: &FunctionPart, span: Span) {
+        walk_function_part(self, part, span)
+    }
+}
+
+pub fn walk_ast<V: Visitor + ?Sized>(visitor: &mut V, ast: &Ast) {
+    match &ast.kind {
+        AstKind::Literal(value) => visitor.visit_literal(value, ast.span),
+        AstKind::Identifier(name) => visitor.visit_identifier(name, ast.span),
+        AstKind::Function(func) => visitor.visit_function(func, ast.span),
+        AstKind::Binary(_, lhs, rhs) | AstKind::Index(lhs, rhs) => {
+            visitor.visit_ast(lhs);
+            visitor.visit_ast(rhs);
+        }
+        AstKind::Array(items) => {
+            for item in items {
+                visitor.visit_ast(item);
+            }
+        }
+        AstKind::Member(target, name, _) => {
+            visitor.visit_ast(target);
+            visitor.visit_member(name, ast.span);
+        }
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, func: &Function) {
+    for (i, part) in func.parts.iter().enumerate() {
+        let span = func.part_spans.get(i).copied().unwrap_or_default();
+        visitor.visit_function_part(part, span);
+    }
+}
+
+/// Field arguments are visited as an identifier followed by their members, each with the
+/// span of the whole argument.
+pub fn walk_function_part<V: Visitor + ?Sized>(visitor: &mut V, part: &FunctionPart, span: Span) {
+    match part {
+        FunctionPart::Identifier(name) => visitor.visit_identifier(name, span),
+        FunctionPart::IdentifierFWithField(root, path) => {
+            visitor.visit_identifier(root, span);
+            for name in path {
+                visitor.visit_member(name, span);
+            }
+        }
+        FunctionPart::CompareExpression(field, _, _) => visitor.visit_filter_field(field, span),
+        FunctionPart::Expression(ast) => visitor.visit_ast(ast),
+    }
+}
+
+/// In-place counterpart of [`Visitor`].
+pub trait VisitorMut {
+    fn visit_ast_mut(&mut self, ast: &mut Ast) {
+        walk_ast_mut(self, ast)
+    }
+
+    fn visit_literal_mut(&mut self, _value: &mut ExpValue, _span: Span) {}
+
+    fn visit_identifier_mut(&mut self, _name: &mut String, _span: Span) {}
+
+    fn visit_member_mut(&mut self, _name: &mut String, _span: Span) {}
+
+    fn visit_filter_field_mut(&mut self, _path: &mut [String], _span: Span) {}
+
+    fn visit_function_mut(&mut self, func: &mut Function, _span: Span) {
+        walk_function_mut(self, func)
+    }
+
+    fn visit_function_part_mut(&mut self, part: &mut FunctionPart, span: Span) {
+        walk_function_part_mut(self, part, span)
+    }
+}
+
+pub fn walk_ast_mut<V: VisitorMut + ?Sized>(visitor: &mut V, ast: &mut Ast) {
+    let span = ast.span;
+    match &mut ast.kind {
+        AstKind::Literal(value) => visitor.visit_literal_mut(value, span),
+        AstKind::Identifier(name) => visitor.visit_identifier_mut(name, span),
+        AstKind::Function(func) => visitor.visit_function_mut(func, span),
+        AstKind::Binary(_, lhs, rhs) | AstKind::Index(lhs, rhs) => {
+            visitor.visit_ast_mut(lhs);
+            visitor.visit_ast_mut(rhs);
+        }
+        AstKind::Array(items) => {
+            for item in items {
+                visitor.visit_ast_mut(item);
+            }
+        }
+        AstKind::Member(target, name, _) => {
+            visitor.visit_ast_mut(target);
+            visitor.visit_member_mut(name, span);
+        }
+    }
+}
+
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(visitor: &mut V, func: &mut Function) {
+    for (i, part) in func.parts.iter_mut().enumerate() {
+        let span = func.part_spans.get(i).copied().unwrap_or_default();
+        visitor.visit_function_part_mut(part, span);
+    }
+}
+
+pub fn walk_function_part_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    part: &mut FunctionPart,
+    span: Span,
+) {
+    match part {
+        FunctionPart::Identifier(name) => visitor.visit_identifier_mut(name, span),
+        FunctionPart::IdentifierFWithField(root, path) => {
+            visitor.visit_identifier_mut(root, span);
+            for name in path {
+                visitor.visit_member_mut(name, span);
+            }
+        }
+        FunctionPart::CompareExpression(field, _, _) => visitor.visit_filter_field_mut(field, span),
+        FunctionPart::Expression(ast) => visitor.visit_ast_mut(ast),
+    }
+}
+
+/// Rebuilds a tree bottom-up. [`fold_children`] folds the children of a node, an
+/// implementation of `fold_ast` usually calls it first and then replaces the node.
+pub trait Fold {
+    fn fold_ast(&mut self, ast: Ast) -> Ast {
+        fold_children(self, ast)
+    }
+
+    /// Expression arguments are folded. The root of a field argument is folded as an
+    /// identifier with the span of the argument, and the argument becomes an expression when
+    /// the root is replaced by anything but an identifier. Filter arguments are kept.
+    fn fold_function_part(&mut self, part: FunctionPart, span: Span) -> FunctionPart {
+        match part {
+            FunctionPart::Expression(ast) => FunctionPart::Expression(self.fold_ast(ast)),
+            FunctionPart::Identifier(name) => match self.fold_ast(identifier(name, span)) {
+                Ast {
+                    kind: AstKind::Identifier(name),
+                    ..
+                } => FunctionPart::Identifier(name),
+                ast => FunctionPart::Expression(ast),
+            },
+            FunctionPart::IdentifierFWithField(root, path) => {
+                match self.fold_ast(identifier(root, span)) {
+                    Ast {
+                        kind: AstKind::Identifier(root),
+                        ..
+                    } => FunctionPart::IdentifierFWithField(root, path),
+                    ast => {
+                        FunctionPart::Expression(path.into_iter().fold(ast, |target, name| Ast {
+                            kind: AstKind::Member(Box::new(target), name, false),
+                            span,
+                        }))
+                    }
+                }
+            }
+            part => part,
+        }
+    }
+}
+
+fn identifier(name: String, span: Span) -> Ast {
+    Ast {
+        kind: AstKind::Identifier(name),
+        span,
+    }
+}
+
+pub fn fold_children<F: Fold + ?Sized>(folder: &mut F, ast: Ast) -> Ast {
+    let kind = match ast.kind {
+        AstKind::Function(mut func) => {
+            let spans = func.part_spans.clone();
+            func.parts = func
+                .parts
+                .into_iter()
+                .enumerate()
+                .map(|(i, part)| {
+                    let span = spans.get(i).copied().unwrap_or_default();
+                    folder.fold_function_part(part, span)
+                })
+                .collect();
+            AstKind::Function(func)
+        }
+        AstKind::Binary(op, lhs, rhs) => AstKind::Binary(
+            op,
+            Box::new(folder.fold_ast(*lhs)),
+            Box::new(folder.fold_ast(*rhs)),
+        ),
+        AstKind::Array(items) => AstKind::Array(
+            items
+                .into_iter()
+                .map(|item| folder.fold_ast(item))
+                .collect(),
+        ),
+        AstKind::Index(target, index) => AstKind::Index(
+            Box::new(folder.fold_ast(*target)),
+            Box::new(folder.fold_ast(*index)),
+        ),
+        AstKind::Member(target, name, optional) => {
+            AstKind::Member(Box::new(folder.fold_ast(*target)), name, optional)
+        }
+        kind => kind,
+    };
+    Ast {
+        kind,
+        span: ast.span,
+    }
+}
+
+impl Ast {
+    pub fn visit<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        visitor.visit_ast(self)
+    }
+
+    pub fn visit_mut<V: VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        visitor.visit_ast_mut(self)
+    }
+
+    pub fn fold<F: Fold + ?Sized>(self, folder: &mut F) -> Ast {
+        folder.fold_ast(self)
+    }
+}
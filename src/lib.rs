@@ -1,5 +1,7 @@
 #[macro_use]
 extern crate pest_derive;
+#[macro_use]
+extern crate lazy_static;
 
 mod calc;
 pub use calc::*;
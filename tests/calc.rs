@@ -8,12 +8,8 @@ mod parse_calc_tests {
         let content = fs::read_to_string("tests/data/data.txt").unwrap();
 
         for formula in content.lines() {
-            let result = formula::parse(&formula);
-            match result {
-                Err(err) => {
-                    assert!(false, "{} Failed: {}", formula, err);
-                }
-                _ => (),
+            if let Err(err) = formula::parse(formula) {
+                panic!("{} Failed: {}", formula, err);
             }
         }
     }
@@ -21,7 +17,7 @@ mod parse_calc_tests {
     #[test]
     fn parse_get_function() {
         let formula = "COUNT(relationship;)";
-        let result = formula::parse(&formula).unwrap();
+        let result = formula::parse(formula).unwrap();
 
         // println!("{:?}", result);
         let function =
@@ -38,7 +34,7 @@ mod parse_calc_tests {
     #[test]
     fn parse_get_function_with_compare() {
         let formula = "COUNT(relationship;issueTypeId=1848788)";
-        let result = formula::parse(&formula).unwrap();
+        let result = formula::parse(formula).unwrap();
 
         let function =
             Function::from(result.clone().next().unwrap().into_inner().next().unwrap()).unwrap();
@@ -48,7 +44,7 @@ mod parse_calc_tests {
             vec![
                 FunctionPart::Identifier("relationship".to_string()),
                 FunctionPart::CompareExpression(
-                    "issueTypeId".to_string(),
+                    vec!["issueTypeId".to_string()],
                     CompareOperator::Equal,
                     "1848788".to_string()
                 )
@@ -59,7 +55,7 @@ mod parse_calc_tests {
     #[test]
     fn parse_get_function_with_compare_2() {
         let formula = "SUM(subtask.estimatePoint;status=4)";
-        let result = formula::parse(&formula).unwrap();
+        let result = formula::parse(formula).unwrap();
 
         let function =
             Function::from(result.clone().next().unwrap().into_inner().next().unwrap()).unwrap();
@@ -67,9 +63,12 @@ mod parse_calc_tests {
         assert_eq!(
             function.parts,
             vec![
-                FunctionPart::Identifier("subtask.estimatePoint".to_string()),
+                FunctionPart::IdentifierFWithField(
+                    "subtask".to_string(),
+                    vec!["estimatePoint".to_string()]
+                ),
                 FunctionPart::CompareExpression(
-                    "status".to_string(),
+                    vec!["status".to_string()],
                     CompareOperator::Equal,
                     "4".to_string()
                 )
@@ -80,7 +79,7 @@ mod parse_calc_tests {
     #[test]
     fn parse_expr() {
         let formula = "SUM(subtask.estimatePoint;status=2) + GET_NOW-GET_UPDATE_TIME";
-        let result = formula::parse(&formula).unwrap();
+        let result = formula::parse(formula).unwrap();
 
         let expr = Expression::from_pairs(result);
 
@@ -90,7 +89,7 @@ mod parse_calc_tests {
     #[test]
     fn get_expr_dependencies_1() {
         let formula = "GET_NOW-GET_UPDATE_TIME";
-        let result = formula::parse(&formula).unwrap();
+        let result = formula::parse(formula).unwrap();
         let expr = Expression::from_pairs(result).unwrap();
 
         let dependencies = expr.get_dependencies();
@@ -107,7 +106,7 @@ mod parse_calc_tests {
     #[test]
     fn get_expr_dependencies_2() {
         let formula = "SUM(subtask.estimatePoint;status=2) + GET_NOW-GET_UPDATE_TIME";
-        let result = formula::parse(&formula).unwrap();
+        let result = formula::parse(formula).unwrap();
         let expr = Expression::from_pairs(result).unwrap();
 
         let dependencies = expr.get_dependencies();
@@ -120,6 +119,40 @@ mod parse_calc_tests {
             vec!["subtask", "GET_NOW", "GET_UPDATE_TIME",]
         );
     }
+
+    fn dependency(root: &str, path: &[&str], role: DependencyRole) -> Dependency {
+        Dependency {
+            root: root.to_string(),
+            path: path.iter().map(|s| s.to_string()).collect(),
+            role,
+        }
+    }
+
+    #[test]
+    fn structured_dependencies() {
+        let formula = "SUM(subtask.estimatePoint;status=2) + GET_NOW-GET_UPDATE_TIME";
+        let expected = vec![
+            dependency("subtask", &["estimatePoint"], DependencyRole::Value),
+            dependency("subtask", &["status"], DependencyRole::Filter),
+            dependency("GET_NOW", &[], DependencyRole::Value),
+            dependency("GET_UPDATE_TIME", &[], DependencyRole::Value),
+        ];
+        assert_eq!(Ast::parse(formula).unwrap().dependencies(), expected);
+        let expr = Expression::from_pairs(formula::parse(formula).unwrap()).unwrap();
+        assert_eq!(expr.dependencies(), expected);
+
+        let formula =
+            "parent.assignee?.name + a[0] * a + COUNT(subtask; type.name=bug) + COUNT(subtask)";
+        assert_eq!(
+            Ast::parse(formula).unwrap().dependencies(),
+            vec![
+                dependency("parent", &["assignee", "name"], DependencyRole::Value),
+                dependency("a", &[], DependencyRole::Value),
+                dependency("subtask", &[], DependencyRole::Value),
+                dependency("subtask", &["type", "name"], DependencyRole::Filter),
+            ]
+        );
+    }
 }
 
 #[cfg(test)]
@@ -296,9 +329,18 @@ mod pass_value_test {
 #[cfg(test)]
 mod function_test {
 
-    use formula::{self, Expression, ExpressionPart, ExpValue};
+    use formula::{self, ExpValue, Expression, ExpressionPart};
     use serde_json::json;
 
+    fn run(formula: &str, json: &serde_json::Value) -> Option<ExpValue> {
+        let exp = formula::parse(formula).unwrap();
+        let func = match Expression::from_pairs(exp).unwrap().parts[0].clone() {
+            ExpressionPart::Function(f) => f,
+            _ => panic!("not function"),
+        };
+        func.run(json)
+    }
+
     #[test]
     fn func_run() {
         let json = json!({
@@ -314,12 +356,1067 @@ mod function_test {
             ]
         });
 
-        let exp = formula::parse("SUM(subtask;status=2)").unwrap();
-        let func = match Expression::from_pairs(exp).unwrap().parts[0].clone() {
-            ExpressionPart::Function(f) => f,
+        // no subtask has a status, so the filter selects none of them
+        let sum = run("SUM(subtask;status=2)", &json);
+        assert!(matches!(sum, Some(ExpValue::Integer(0))), "{:?}", sum);
+    }
+
+    #[test]
+    fn func_run_filtered() {
+        let json = json!({
+            "subtask": [
+                {"id": 1, "name": "test1", "status": 2},
+                {"id": 2, "name": "test2", "status": 2},
+                {"id": 4, "name": "test3", "status": 1},
+            ]
+        });
+
+        let sum = run("SUM(subtask;status=2)", &json);
+        assert!(matches!(sum, Some(ExpValue::Integer(3))), "{:?}", sum);
+        let count = run("COUNT(subtask;status<>2)", &json);
+        assert!(matches!(count, Some(ExpValue::Integer(1))), "{:?}", count);
+        let sum = run("SUM(subtask)", &json);
+        assert!(matches!(sum, Some(ExpValue::Integer(7))), "{:?}", sum);
+    }
+}
+
+#[cfg(test)]
+mod date_calc_tests {
+    use formula::{self, ExpValue, ValueError, MILLIS_PER_DAY, MILLIS_PER_HOUR, MILLIS_PER_MINUTE};
+    use std::collections::HashMap;
+
+    fn create_date_table() -> HashMap<String, ExpValue> {
+        let mut table = HashMap::new();
+
+        // 2024-03-10T00:00:00Z
+        table.insert("GET_NOW".to_string(), ExpValue::Date(1710028800000));
+        // 2024-03-01T00:00:00Z
+        table.insert("dueDate".to_string(), ExpValue::Date(1709251200000));
+
+        table
+    }
+
+    #[test]
+    fn parse_literals() {
+        for formula in [
+            "#2024-03-01#",
+            "#2024-03-01T10:00:00Z#",
+            "2024-03-01T10:00:00+08:00",
+            "3d",
+            "4h30m",
+            "dueDate - 3d",
+            "GET_NOW - dueDate - 1w2d",
+        ] {
+            assert!(formula::parse(formula).is_ok(), "{}", formula);
+        }
+    }
+
+    #[test]
+    fn non_ascii_literals() {
+        for input in [
+            "2024-01-01T10:00:00.ééé",
+            "2024-01-01T10:00+aéb",
+            "2024-01-01T10:00+é",
+            "2024-01-01T10:00:00.5x",
+            "2024-01-01T10:00:00.",
+            "é-01-01",
+        ] {
+            assert_eq!(formula::parse_date_literal(input), None, "{}", input);
+        }
+        assert_eq!(
+            formula::parse_date_literal("2024-01-01T10:00:00.25+01:00"),
+            Some(1704099600250)
+        );
+        assert_eq!(formula::parse_duration_literal("3dé"), None);
+        assert_eq!(formula::parse_duration_literal("é3d"), None);
+    }
+
+    #[test]
+    fn eval_date_literal() {
+        let table = HashMap::new();
+        let result = formula::eval(formula::parse("#2024-03-01#").unwrap(), &table);
+        assert_eq!(result, ExpValue::Date(1709251200000));
+
+        let result = formula::eval(formula::parse("2024-03-01T10:00:00+02:00").unwrap(), &table);
+        assert_eq!(result, ExpValue::Date(1709251200000 + 8 * MILLIS_PER_HOUR));
+    }
+
+    #[test]
+    fn eval_duration_literal() {
+        let table = HashMap::new();
+        let result = formula::eval(formula::parse("4h30m").unwrap(), &table);
+        assert_eq!(
+            result,
+            ExpValue::Duration(4 * MILLIS_PER_HOUR + 30 * MILLIS_PER_MINUTE)
+        );
+    }
+
+    #[test]
+    fn eval_date_arithmetic() {
+        let table = create_date_table();
+
+        let result = formula::eval(formula::parse("dueDate - 3d").unwrap(), &table);
+        assert_eq!(result, ExpValue::Date(1709251200000 - 3 * MILLIS_PER_DAY));
+
+        let result = formula::eval(formula::parse("GET_NOW - dueDate").unwrap(), &table);
+        assert_eq!(result, ExpValue::Duration(9 * MILLIS_PER_DAY));
+
+        let result = formula::eval(formula::parse("(GET_NOW - dueDate) / 1d").unwrap(), &table);
+        assert_eq!(result, ExpValue::Number(9.0));
+
+        let result = formula::eval(formula::parse("dueDate - #2024-02-29#").unwrap(), &table);
+        assert_eq!(result, ExpValue::Duration(MILLIS_PER_DAY));
+    }
+
+    #[test]
+    fn invalid_date_literal() {
+        let result = formula::eval(formula::parse("#2023-02-29#").unwrap(), &HashMap::new());
+        assert_eq!(result, ExpValue::Error(ValueError::InvalidLiteral));
+    }
+
+    #[test]
+    fn date_overflow() {
+        let mut table = HashMap::new();
+        table.insert("max".to_string(), ExpValue::Date(i64::MAX));
+        table.insert("min".to_string(), ExpValue::Date(i64::MIN));
+        for formula in [
+            "9223372036854775807ms + 1ms",
+            "#2024-01-01# + 9223372036854775807ms",
+            "9223372036854775807ms + #2024-01-01#",
+            "max - min",
+            "min - 1ms",
+            "0ms - 9223372036854775807ms - 2ms",
+            "1d * 1e300",
+            "1e300 * 1d",
+            "1d / 1e-300",
+            "(0d - 1d) * 1e300",
+        ] {
+            let result = formula::eval(formula::parse(formula).unwrap(), &table);
+            assert_eq!(result, ExpValue::Error(ValueError::Overflow), "{}", formula);
+        }
+        assert_eq!(
+            formula::eval(formula::parse("1d * 0.5").unwrap(), &table),
+            ExpValue::Duration(12 * MILLIS_PER_HOUR)
+        );
+
+        let mut options = formula::EvalOptions {
+            division_by_zero: formula::DivisionByZero::Ieee,
+            ..Default::default()
+        };
+        let eval = |formula: &str, options: &formula::EvalOptions| {
+            formula::eval_with(formula::parse(formula).unwrap(), &table, options)
+        };
+        assert_eq!(
+            eval("1d / 0", &options),
+            ExpValue::Error(ValueError::Overflow)
+        );
+        assert_eq!(eval("0d / 0", &options), ExpValue::Error(ValueError::NaN));
+        options.allow_nan = true;
+        assert!(matches!(eval("0d / 0", &options), ExpValue::Number(n) if n.is_nan()));
+        options.division_by_zero = formula::DivisionByZero::Null;
+        assert_eq!(eval("0d / 0", &options), ExpValue::Null);
+    }
+}
+
+#[cfg(test)]
+mod ast_tests {
+    use formula::{self, Ast, AstKind, BinaryOperator, ExpValue, Expected, Span};
+    use std::collections::HashMap;
+
+    #[test]
+    fn parse_ast() {
+        let ast = Ast::parse("a + b * 2").unwrap();
+
+        assert_eq!(ast.span, Span { start: 0, end: 9 });
+        match ast.kind {
+            AstKind::Binary(BinaryOperator::Add, lhs, rhs) => {
+                assert_eq!(lhs.kind, AstKind::Identifier("a".to_string()));
+                match rhs.kind {
+                    AstKind::Binary(BinaryOperator::Multiply, _, rhs) => {
+                        assert_eq!(rhs.kind, AstKind::Literal(ExpValue::Number(2.0)));
+                        assert_eq!(rhs.span, Span { start: 8, end: 9 });
+                    }
+                    _ => panic!("not multiply"),
+                }
+            }
+            _ => panic!("not add"),
+        }
+    }
+
+    #[test]
+    fn parse_ast_function() {
+        let ast = Ast::parse("COUNT(subtask;status=4)").unwrap();
+        match ast.kind {
+            AstKind::Function(func) => assert_eq!(func.name, "COUNT"),
+            _ => panic!("not function"),
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = Ast::parse("COUNT(relationship;issueTypeId=)").unwrap_err();
+        assert_eq!((err.offset, err.line, err.column), (31, 1, 32));
+        assert_eq!(err.found, Some(')'));
+        assert!(err.expected.contains(&Expected::Field));
+        assert!(err.expected.contains(&Expected::Number));
+        assert_eq!(
+            err.to_string(),
+            "expected a field name, a number, a date, a duration or a list at 1:32, found `)`"
+        );
+
+        let err = Ast::parse("(a + b").unwrap_err();
+        assert_eq!(err.expected[0], Expected::Close(')'));
+        assert_eq!(err.found, None);
+
+        let err = Ast::parse("a +\n  * b").unwrap_err();
+        assert_eq!((err.offset, err.char_offset), (6, 6));
+        assert_eq!((err.line, err.column), (2, 3));
+
+        let err = Ast::parse("1 + 2)").unwrap_err();
+        assert_eq!(
+            err.expected,
+            vec![Expected::Operator, Expected::Accessor, Expected::End]
+        );
+    }
+
+    #[test]
+    fn calc_shift() {
+        let table = HashMap::new();
+        let result = formula::eval(formula::parse("1 << 2 + 1").unwrap(), &table);
+        assert_eq!(result, ExpValue::Number(8.0));
+
+        let result = formula::eval(formula::parse("16 >> 2").unwrap(), &table);
+        assert_eq!(result, ExpValue::Number(4.0));
+    }
+}
+
+#[cfg(all(test, feature = "decimal"))]
+mod decimal_calc_tests {
+    use formula::{self, Decimal, DecimalOptions, EvalOptions, ExpValue, RoundingMode, ValueError};
+    use std::{collections::HashMap, str::FromStr};
+
+    fn decimal(value: &str) -> ExpValue {
+        ExpValue::Decimal(Decimal::from_str(value).unwrap())
+    }
+
+    fn eval_decimal(formula: &str, table: &HashMap<String, ExpValue>, precision: u32) -> ExpValue {
+        let options = EvalOptions {
+            decimal: Some(DecimalOptions {
+                precision,
+                rounding: RoundingMode::HalfUp,
+            }),
+            ..Default::default()
+        };
+        formula::eval_with(formula::parse(formula).unwrap(), table, &options)
+    }
+
+    #[test]
+    fn calc_exact() {
+        let table = HashMap::new();
+        assert_eq!(eval_decimal("0.1 + 0.2", &table, 10), decimal("0.3"));
+        assert_eq!(eval_decimal("6 / 3 / 5 / 8", &table, 10), decimal("0.05"));
+        assert_eq!(eval_decimal("1.1 ^ 2", &table, 10), decimal("1.21"));
+    }
+
+    #[test]
+    fn calc_fields() {
+        let mut table = HashMap::new();
+        table.insert("customfield_19880148".to_string(), ExpValue::Number(19.99));
+        table.insert("customfield_19883800".to_string(), ExpValue::Number(3.0));
+        table.insert("customfield_19880237".to_string(), decimal("1.1"));
+        table.insert("customfield_20662419".to_string(), ExpValue::Number(7.0));
+
+        let result = eval_decimal(
+            "customfield_19880148*customfield_19883800*customfield_19880237/customfield_20662419",
+            &table,
+            2,
+        );
+        assert_eq!(result, decimal("9.42"));
+    }
+
+    #[test]
+    fn calc_sum() {
+        let mut table = HashMap::new();
+        table.insert(
+            "sub".to_string(),
+            ExpValue::Array(vec![
+                ExpValue::Object([("p".to_string(), ExpValue::Number(0.1))].into()),
+                ExpValue::Object([("p".to_string(), ExpValue::Number(0.2))].into()),
+                ExpValue::Object([("p".to_string(), ExpValue::Number(0.3))].into()),
+            ]),
+        );
+        let result = eval_decimal("SUM(sub.p)", &table, 10);
+        assert!(matches!(result, ExpValue::Decimal(_)), "{:?}", result);
+        assert_eq!(result, decimal("0.6"));
+        assert_eq!(eval_decimal("SUM(sub.p) / 7", &table, 2), decimal("0.09"));
+    }
+
+    #[test]
+    fn calc_rounding_mode() {
+        let options = EvalOptions {
+            decimal: Some(DecimalOptions {
+                precision: 0,
+                rounding: RoundingMode::HalfEven,
+            }),
+            ..Default::default()
+        };
+        let result =
+            formula::eval_with(formula::parse("5 / 2").unwrap(), &HashMap::new(), &options);
+        assert_eq!(result, decimal("2"));
+    }
+
+    #[test]
+    fn calc_division_by_zero() {
+        assert_eq!(
+            eval_decimal("1 / 0", &HashMap::new(), 10),
+            ExpValue::Error(ValueError::DivByZero)
+        );
+    }
+}
+
+#[cfg(test)]
+mod integer_calc_tests {
+    use formula::{self, ExpValue, Expression, ExpressionPart};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn calc(formula: &str, table: &HashMap<String, ExpValue>) -> ExpValue {
+        formula::eval(formula::parse(formula).unwrap(), table)
+    }
+
+    fn is_integer(value: &ExpValue) -> bool {
+        matches!(value, ExpValue::Integer(_))
+    }
+
+    #[test]
+    fn integer_literals() {
+        let table = HashMap::new();
+        assert!(is_integer(&calc("2 + 3 * 4", &table)));
+        assert_eq!(calc("2 + 3 * 4", &table), ExpValue::Integer(14));
+        assert!(!is_integer(&calc("2 + 3.0", &table)));
+        assert!(!is_integer(&calc("7 / 2", &table)));
+        assert_eq!(calc("7 / 2", &table), ExpValue::Number(3.5));
+    }
+
+    #[test]
+    fn integer_fields() {
+        let mut table = HashMap::new();
+        table.insert(
+            "estimatePoint".to_string(),
+            ExpValue::Integer(9_007_199_254_740_993),
+        );
+
+        let result = calc("estimatePoint * 1", &table);
+        assert!(matches!(result, ExpValue::Integer(9_007_199_254_740_993)));
+
+        table.insert(
+            "estimatePoint".to_string(),
+            ExpValue::Integer(5_000_000_000),
+        );
+        let result = calc("estimatePoint*estimatePoint", &table);
+        assert!(!is_integer(&result));
+        assert_eq!(result, ExpValue::Number(2.5e19));
+    }
+
+    #[test]
+    fn integer_division() {
+        let table = HashMap::new();
+        assert_eq!(calc("7 // 2", &table), ExpValue::Integer(3));
+        assert_eq!(calc("-7 div 2", &table), ExpValue::Integer(-4));
+        assert_eq!(calc("7.5 // 2", &table), ExpValue::Number(3.0));
+        assert_eq!(calc("2 * 7 // 2", &table), ExpValue::Integer(7));
+        assert_eq!(calc("10d // 3d", &table), ExpValue::Integer(3));
+    }
+
+    #[test]
+    fn integer_pow_and_rem() {
+        let table = HashMap::new();
+        assert!(is_integer(&calc("2 ^ 10", &table)));
+        assert_eq!(calc("2 ^ -1", &table), ExpValue::Number(0.5));
+        assert!(!is_integer(&calc("2 ^ 64", &table)));
+        assert_eq!(calc("7 % 3", &table), ExpValue::Integer(1));
+
+        let mut table = HashMap::new();
+        table.insert("min".to_string(), ExpValue::Integer(i64::MIN));
+        assert_eq!(calc("min % -1", &table), ExpValue::Integer(0));
+        assert!(is_integer(&calc("min % -1", &table)));
+    }
+
+    #[test]
+    fn integer_shift_overflow() {
+        let table = HashMap::new();
+        assert_eq!(calc("3 << 2", &table), ExpValue::Integer(12));
+        assert_eq!(calc("-1 << 63", &table), ExpValue::Integer(i64::MIN));
+        assert_eq!(
+            calc("9223372036854775807 << 3", &table),
+            ExpValue::Number(9223372036854775807.0 * 8.0)
+        );
+        assert!(!is_integer(&calc("9223372036854775807 << 3", &table)));
+        assert_eq!(calc("1 << 63", &table), ExpValue::Number(2f64.powi(63)));
+        assert_eq!(calc("1.5 << 2", &table), ExpValue::Number(4.0));
+    }
+
+    #[test]
+    fn integer_sum_and_count() {
+        let json = json!({
+            "subtask": [{ "id": 1 }, { "id": 2 }, { "id": 4 }]
+        });
+
+        let run = |formula: &str| match Expression::from_pairs(formula::parse(formula).unwrap())
+            .unwrap()
+            .parts[0]
+            .clone()
+        {
+            ExpressionPart::Function(f) => f.run(&json),
             _ => panic!("not function"),
         };
-        let sum = func.run(&json);
-        assert_eq!(sum, Some(ExpValue::Number(3.0)));
+
+        assert!(matches!(run("SUM(subtask;)"), Some(ExpValue::Integer(7))));
+        assert!(matches!(run("COUNT(subtask;)"), Some(ExpValue::Integer(3))));
+    }
+}
+
+#[cfg(test)]
+mod division_policy_tests {
+    use formula::{self, DivisionByZero, EvalOptions, ExpValue, ValueError};
+    use std::collections::{BTreeMap, HashMap};
+
+    fn calc(formula: &str, options: &EvalOptions) -> ExpValue {
+        let mut table = HashMap::new();
+        table.insert("zero".to_string(), ExpValue::Number(0.0));
+        table.insert("nan".to_string(), ExpValue::Number(f64::NAN));
+        table.insert(
+            "record".to_string(),
+            ExpValue::Object(BTreeMap::from([
+                ("n".to_string(), ExpValue::Number(f64::NAN)),
+                ("m".to_string(), ExpValue::Integer(1)),
+            ])),
+        );
+
+        formula::eval_with(formula::parse(formula).unwrap(), &table, options)
+    }
+
+    fn with_policy(division_by_zero: DivisionByZero) -> EvalOptions {
+        EvalOptions {
+            division_by_zero,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_policy() {
+        let options = EvalOptions::default();
+        assert_eq!(
+            calc("1 / zero", &options),
+            ExpValue::Error(ValueError::DivByZero)
+        );
+        assert_eq!(
+            calc("7 // 0", &options),
+            ExpValue::Error(ValueError::DivByZero)
+        );
+        assert_eq!(
+            calc("7 % 0", &options),
+            ExpValue::Error(ValueError::DivByZero)
+        );
+        assert_eq!(
+            calc("1d / 0", &options),
+            ExpValue::Error(ValueError::DivByZero)
+        );
+        assert_eq!(calc("1 / 2", &options), ExpValue::Number(0.5));
+    }
+
+    #[test]
+    fn ieee_policy() {
+        let options = with_policy(DivisionByZero::Ieee);
+        assert_eq!(calc("1 / zero", &options), ExpValue::Number(f64::INFINITY));
+        assert_eq!(calc("0 / zero", &options), ExpValue::Error(ValueError::NaN));
+
+        let options = EvalOptions {
+            allow_nan: true,
+            ..options
+        };
+        assert!(matches!(calc("0 / zero", &options), ExpValue::Number(n) if n.is_nan()));
+    }
+
+    #[test]
+    fn null_and_value_policies() {
+        assert_eq!(
+            calc("1 / 0", &with_policy(DivisionByZero::Null)),
+            ExpValue::Null
+        );
+        assert_eq!(
+            calc(
+                "1 / 0",
+                &with_policy(DivisionByZero::Value(ExpValue::Integer(0)))
+            ),
+            ExpValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn nan_never_escapes() {
+        let options = EvalOptions::default();
+        assert_eq!(calc("nan", &options), ExpValue::Error(ValueError::NaN));
+        assert_eq!(calc("nan + 1", &options), ExpValue::Error(ValueError::NaN));
+        assert_eq!(
+            calc("(0 - 8) ^ 0.5", &options),
+            ExpValue::Error(ValueError::NaN)
+        );
+        assert_eq!(
+            calc("record", &options),
+            ExpValue::Object(BTreeMap::from([
+                ("n".to_string(), ExpValue::Error(ValueError::NaN)),
+                ("m".to_string(), ExpValue::Integer(1)),
+            ]))
+        );
+        assert_eq!(
+            calc("[record][0].n", &options),
+            ExpValue::Error(ValueError::NaN)
+        );
+    }
+
+    #[test]
+    fn error_kinds() {
+        let options = EvalOptions::default();
+        assert_eq!(
+            calc("missing + 1", &options),
+            ExpValue::Error(ValueError::UnknownIdentifier)
+        );
+        assert_eq!(
+            calc("1d + 1", &options),
+            ExpValue::Error(ValueError::TypeMismatch)
+        );
+    }
+}
+
+#[cfg(test)]
+mod null_calc_tests {
+    use std::collections::HashMap;
+
+    use formula::{EvalOptions, ExpValue, NullMode, ValueError};
+
+    fn calc(formula: &str, options: &EvalOptions) -> ExpValue {
+        let mut table = HashMap::new();
+        table.insert("blank".to_string(), ExpValue::Null);
+        table.insert("points".to_string(), ExpValue::Integer(3));
+        table.insert("due".to_string(), ExpValue::Date(86_400_000));
+        table.insert(
+            "estimates".to_string(),
+            ExpValue::Array(vec![
+                ExpValue::Integer(1),
+                ExpValue::Null,
+                ExpValue::Integer(2),
+            ]),
+        );
+
+        formula::eval_with(formula::parse(formula).unwrap(), &table, options)
+    }
+
+    #[test]
+    fn null_propagates() {
+        let options = EvalOptions::default();
+        assert_eq!(calc("blank + 1", &options), ExpValue::Null);
+        assert_eq!(calc("points * blank", &options), ExpValue::Null);
+        assert_eq!(calc("due - blank", &options), ExpValue::Null);
+        assert_eq!(calc("1 / blank", &options), ExpValue::Null);
+        assert_eq!(
+            calc("missing + blank", &options),
+            ExpValue::Error(ValueError::UnknownIdentifier)
+        );
+    }
+
+    #[test]
+    fn null_as_zero() {
+        let options = EvalOptions {
+            null_mode: NullMode::Zero,
+            ..Default::default()
+        };
+        assert_eq!(calc("blank + 1", &options), ExpValue::Integer(1));
+        assert_eq!(calc("points * blank", &options), ExpValue::Integer(0));
+        assert_eq!(calc("due - blank", &options), ExpValue::Date(86_400_000));
+        assert_eq!(
+            calc("1 / blank", &options),
+            ExpValue::Error(ValueError::DivByZero)
+        );
+    }
+
+    #[test]
+    fn coalesce_and_ifnull() {
+        let options = EvalOptions::default();
+        assert_eq!(
+            calc("COALESCE(blank; points; 0)", &options),
+            ExpValue::Integer(3)
+        );
+        assert_eq!(calc("COALESCE(blank; blank)", &options), ExpValue::Null);
+        assert_eq!(
+            calc("COALESCE(blank; 0) + 1", &options),
+            ExpValue::Integer(1)
+        );
+        assert_eq!(
+            calc("IFNULL(blank; 2 * points)", &options),
+            ExpValue::Integer(6)
+        );
+        assert_eq!(calc("IFNULL(points; 0)", &options), ExpValue::Integer(3));
+        assert_eq!(
+            calc("IFNULL(blank)", &options),
+            ExpValue::Error(ValueError::InvalidArguments)
+        );
+    }
+
+    #[test]
+    fn isblank_and_iserror() {
+        let options = EvalOptions::default();
+        assert_eq!(calc("ISBLANK(blank)", &options), ExpValue::Bool(true));
+        assert_eq!(calc("ISBLANK(points)", &options), ExpValue::Bool(false));
+        assert_eq!(calc("ISERROR(points / 0)", &options), ExpValue::Bool(true));
+        assert_eq!(calc("ISERROR(missing)", &options), ExpValue::Bool(true));
+        assert_eq!(calc("ISERROR(blank + 1)", &options), ExpValue::Bool(false));
+    }
+
+    #[test]
+    fn aggregates_skip_nulls() {
+        let options = EvalOptions::default();
+        assert_eq!(calc("SUM(estimates)", &options), ExpValue::Integer(3));
+        assert_eq!(calc("COUNT(estimates)", &options), ExpValue::Integer(3));
+        assert_eq!(calc("COUNT(blank)", &options), ExpValue::Integer(0));
+    }
+}
+
+#[cfg(test)]
+mod coercion_calc_tests {
+    use std::collections::HashMap;
+
+    use formula::{Coercion, EvalOptions, ExpValue, ValueError};
+
+    fn calc(formula: &str, coercion: Coercion) -> ExpValue {
+        let mut table = HashMap::new();
+        table.insert("points".to_string(), ExpValue::String("123".to_string()));
+        table.insert("ratio".to_string(), ExpValue::String(" 0.5 ".to_string()));
+        table.insert("title".to_string(), ExpValue::String("abc".to_string()));
+        table.insert("done".to_string(), ExpValue::Bool(true));
+        table.insert(
+            "estimates".to_string(),
+            ExpValue::Array(vec![
+                ExpValue::String("1".to_string()),
+                ExpValue::Integer(2),
+            ]),
+        );
+
+        let options = EvalOptions {
+            coercion,
+            ..Default::default()
+        };
+        formula::eval_with(formula::parse(formula).unwrap(), &table, &options)
+    }
+
+    #[test]
+    fn strict_rejects_strings_and_bools() {
+        assert_eq!(
+            calc("points + 1", Coercion::Strict),
+            ExpValue::Error(ValueError::TypeMismatch)
+        );
+        assert_eq!(
+            calc("done * 2", Coercion::Strict),
+            ExpValue::Error(ValueError::TypeMismatch)
+        );
+    }
+
+    #[test]
+    fn lenient_parses_numeric_strings() {
+        assert_eq!(
+            calc("points + 1", Coercion::Lenient),
+            ExpValue::Integer(124)
+        );
+        assert_eq!(calc("ratio * 4", Coercion::Lenient), ExpValue::Number(2.0));
+        assert_eq!(calc("points % 10", Coercion::Lenient), ExpValue::Integer(3));
+        assert_eq!(
+            calc("done * 2 + done", Coercion::Lenient),
+            ExpValue::Integer(3)
+        );
+        assert_eq!(
+            calc("SUM(estimates)", Coercion::Lenient),
+            ExpValue::Integer(3)
+        );
+        assert_eq!(
+            calc("title + 1", Coercion::Lenient),
+            ExpValue::Error(ValueError::TypeMismatch)
+        );
+    }
+}
+
+#[cfg(test)]
+mod array_calc_tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use formula::{EvalOptions, ExpValue, ValueError};
+
+    fn ints(values: &[i64]) -> ExpValue {
+        ExpValue::Array(values.iter().map(|v| ExpValue::Integer(*v)).collect())
+    }
+
+    fn calc(formula: &str) -> ExpValue {
+        let mut table = HashMap::new();
+        table.insert(
+            "subtask".to_string(),
+            ExpValue::Array(
+                [(3, 1), (5, 2), (8, 0)]
+                    .iter()
+                    .map(|(estimate_point, weight)| {
+                        let mut fields = BTreeMap::new();
+                        fields.insert(
+                            "estimatePoint".to_string(),
+                            ExpValue::Integer(*estimate_point),
+                        );
+                        fields.insert("weight".to_string(), ExpValue::Integer(*weight));
+                        ExpValue::Object(fields)
+                    })
+                    .collect(),
+            ),
+        );
+        table.insert("labels".to_string(), ints(&[]));
+
+        formula::eval_with(
+            formula::parse(formula).unwrap(),
+            &table,
+            &EvalOptions::default(),
+        )
+    }
+
+    #[test]
+    fn array_literals() {
+        assert_eq!(calc("[1, 2, 3]"), ints(&[1, 2, 3]));
+        assert_eq!(calc("[]"), ints(&[]));
+        assert_eq!(
+            calc("[1 + 1, [2]]"),
+            ExpValue::Array(vec![ExpValue::Integer(2), ints(&[2])])
+        );
+    }
+
+    #[test]
+    fn broadcasting() {
+        assert_eq!(calc("subtask.estimatePoint * 2"), ints(&[6, 10, 16]));
+        assert_eq!(calc("10 - [1, 2]"), ints(&[9, 8]));
+        assert_eq!(
+            calc("subtask.estimatePoint * subtask.weight"),
+            ints(&[3, 10, 0])
+        );
+        assert_eq!(
+            calc("[1, 2] + [1, 2, 3]"),
+            ExpValue::Error(ValueError::LengthMismatch)
+        );
+        assert_eq!(
+            calc("[1, 2] / 0"),
+            ExpValue::Array(vec![
+                ExpValue::Error(ValueError::DivByZero),
+                ExpValue::Error(ValueError::DivByZero),
+            ])
+        );
+    }
+
+    #[test]
+    fn indexing() {
+        assert_eq!(calc("[1, 2, 3][0]"), ExpValue::Integer(1));
+        assert_eq!(
+            calc("subtask.estimatePoint[1 + 1] * 2"),
+            ExpValue::Integer(16)
+        );
+        assert_eq!(calc("[[1, 2], [3]][1][0]"), ExpValue::Integer(3));
+        assert_eq!(calc("labels[0]"), ExpValue::Null);
+        assert_eq!(
+            calc("[1, 2][0.5]"),
+            ExpValue::Error(ValueError::TypeMismatch)
+        );
+    }
+
+    #[test]
+    fn aggregates_over_expressions() {
+        assert_eq!(
+            calc("SUM(subtask.estimatePoint * subtask.weight)"),
+            ExpValue::Integer(13)
+        );
+        assert_eq!(calc("SUM(subtask.estimatePoint)"), ExpValue::Integer(16));
+        assert_eq!(calc("SUM([1.5, 2]) * 2"), ExpValue::Number(7.0));
+        assert_eq!(calc("COUNT(labels)"), ExpValue::Integer(0));
+    }
+}
+
+#[cfg(test)]
+mod member_calc_tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use formula::{EvalOptions, ExpValue, FunctionPart, ValueError};
+
+    fn object(fields: Vec<(&str, ExpValue)>) -> ExpValue {
+        ExpValue::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    fn calc(formula: &str) -> ExpValue {
+        let mut table = HashMap::new();
+        table.insert(
+            "parent".to_string(),
+            object(vec![
+                (
+                    "assignee",
+                    object(vec![("name", ExpValue::String("Ann".to_string()))]),
+                ),
+                ("reviewer", ExpValue::Null),
+                ("estimatePoint", ExpValue::Integer(5)),
+            ]),
+        );
+        table.insert(
+            "subtask".to_string(),
+            ExpValue::Array(vec![
+                object(vec![
+                    ("estimatePoint", ExpValue::Integer(3)),
+                    ("status", ExpValue::Integer(4)),
+                ]),
+                object(vec![
+                    ("estimatePoint", ExpValue::Integer(5)),
+                    ("status", ExpValue::Integer(1)),
+                ]),
+                object(vec![
+                    ("estimatePoint", ExpValue::Integer(8)),
+                    ("status", ExpValue::Integer(4)),
+                ]),
+            ]),
+        );
+        table.insert(
+            "x".to_string(),
+            ExpValue::Array(vec![object(vec![
+                ("a.b", ExpValue::Integer(1)),
+                ("a", object(vec![("b", ExpValue::Integer(5))])),
+            ])]),
+        );
+
+        formula::eval_with(
+            formula::parse(formula).unwrap(),
+            &table,
+            &EvalOptions::default(),
+        )
+    }
+
+    #[test]
+    fn deep_member_access() {
+        assert_eq!(
+            calc("parent.assignee.name"),
+            ExpValue::String("Ann".to_string())
+        );
+        assert_eq!(calc("parent.estimatePoint * 2"), ExpValue::Integer(10));
+        assert_eq!(calc("parent.missing"), ExpValue::Null);
+        assert_eq!(calc("subtask[2].estimatePoint"), ExpValue::Integer(8));
+        assert_eq!(
+            calc("subtask.status"),
+            ExpValue::Array(vec![
+                ExpValue::Integer(4),
+                ExpValue::Integer(1),
+                ExpValue::Integer(4),
+            ])
+        );
+        assert_eq!(
+            calc("parent.estimatePoint.value"),
+            ExpValue::Error(ValueError::TypeMismatch)
+        );
+    }
+
+    #[test]
+    fn null_safe_navigation() {
+        assert_eq!(calc("parent.reviewer?.name"), ExpValue::Null);
+        assert_eq!(calc("parent?.reviewer?.name?.first"), ExpValue::Null);
+        assert_eq!(
+            calc("parent.reviewer.name"),
+            ExpValue::Error(ValueError::NullMember)
+        );
+        assert_eq!(
+            calc("COALESCE(parent.reviewer?.name; parent.assignee.name)"),
+            ExpValue::String("Ann".to_string())
+        );
+    }
+
+    #[test]
+    fn function_parts_keep_full_path() {
+        let func = formula::Function::from(
+            formula::parse("COUNT(parent.assignee.name)")
+                .unwrap()
+                .next()
+                .unwrap()
+                .into_inner()
+                .next()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            func.parts,
+            vec![FunctionPart::IdentifierFWithField(
+                "parent".to_string(),
+                vec!["assignee".to_string(), "name".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn aggregates_with_filters() {
+        assert_eq!(calc("SUM(subtask.estimatePoint)"), ExpValue::Integer(16));
+        assert_eq!(
+            calc("SUM(subtask.estimatePoint;status=4)"),
+            ExpValue::Integer(11)
+        );
+        assert_eq!(calc("COUNT(subtask;status=4)"), ExpValue::Integer(2));
+        assert_eq!(calc("COUNT(subtask;status=2)"), ExpValue::Integer(0));
+    }
+
+    #[test]
+    fn dotted_braced_names() {
+        assert_eq!(calc("SUM(x.{a.b})"), ExpValue::Integer(1));
+        assert_eq!(calc("SUM(x.a.b)"), ExpValue::Integer(5));
+        assert_eq!(calc("COUNT(x; {a.b}=1)"), ExpValue::Integer(1));
+        assert_eq!(calc("COUNT(x; a.b=1)"), ExpValue::Integer(0));
+        assert_eq!(calc("SUM(x.{a.b}; {a.b}=1)"), ExpValue::Integer(1));
+
+        let formula = "SUM(x.{a.b}; {a.b} = 1)";
+        assert_eq!(formula::format(formula).unwrap(), formula);
+        let dependencies = formula::Ast::parse(formula).unwrap().dependencies();
+        assert_eq!(dependencies[0].path, vec!["a.b"]);
+        assert_eq!(dependencies[1].path, vec!["a.b"]);
+    }
+}
+
+#[cfg(test)]
+mod compare_calc_tests {
+    use std::{
+        cmp::Ordering,
+        collections::{BTreeMap, HashMap},
+    };
+
+    use formula::{EvalOptions, ExpValue, ValueError};
+
+    fn task(estimate_point: i64, status: i64) -> ExpValue {
+        let mut fields = BTreeMap::new();
+        fields.insert(
+            "estimatePoint".to_string(),
+            ExpValue::Integer(estimate_point),
+        );
+        fields.insert("status".to_string(), ExpValue::Integer(status));
+        ExpValue::Object(fields)
+    }
+
+    fn calc_with(formula: &str, options: &EvalOptions) -> ExpValue {
+        let mut table = HashMap::new();
+        table.insert("dueDate".to_string(), ExpValue::Date(1709251200000));
+        table.insert("title".to_string(), ExpValue::String("Login".to_string()));
+        table.insert("blank".to_string(), ExpValue::Null);
+        table.insert(
+            "subtask".to_string(),
+            ExpValue::Array(vec![task(3, 4), task(5, 1), task(8, 4), task(2, 3)]),
+        );
+
+        formula::eval_with(formula::parse(formula).unwrap(), &table, options)
+    }
+
+    fn calc(formula: &str) -> ExpValue {
+        calc_with(formula, &EvalOptions::default())
+    }
+
+    #[test]
+    fn comparison_operators() {
+        assert_eq!(calc("1 + 1 = 2"), ExpValue::Bool(true));
+        assert_eq!(calc("2 <> 2.0"), ExpValue::Bool(false));
+        assert_eq!(calc("3 != 2"), ExpValue::Bool(true));
+        assert_eq!(calc("2 * 3 >= 6"), ExpValue::Bool(true));
+        assert_eq!(calc("1 << 2 < 5"), ExpValue::Bool(true));
+        assert_eq!(calc("dueDate < #2024-03-02#"), ExpValue::Bool(true));
+        assert_eq!(calc("dueDate + 1d > #2024-03-02#"), ExpValue::Bool(false));
+        assert_eq!(calc("title == title"), ExpValue::Bool(true));
+        assert_eq!(calc("title = 1"), ExpValue::Bool(false));
+        assert_eq!(calc("blank = 1"), ExpValue::Null);
+        assert_eq!(calc("title < 1"), ExpValue::Error(ValueError::TypeMismatch));
+        assert_eq!(
+            calc("subtask.status = 4"),
+            ExpValue::Array(vec![
+                ExpValue::Bool(true),
+                ExpValue::Bool(false),
+                ExpValue::Bool(true),
+                ExpValue::Bool(false),
+            ])
+        );
+    }
+
+    #[test]
+    fn tolerant_equality() {
+        assert_eq!(calc("0.1 + 0.2 = 0.3"), ExpValue::Bool(true));
+        assert_eq!(calc("0.1 + 0.2 > 0.3"), ExpValue::Bool(false));
+
+        let exact = EvalOptions {
+            epsilon: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(calc_with("0.1 + 0.2 = 0.3", &exact), ExpValue::Bool(false));
+        assert_eq!(calc_with("0.1 + 0.2 > 0.3", &exact), ExpValue::Bool(true));
+    }
+
+    #[test]
+    fn min_and_max() {
+        assert_eq!(calc("MIN(3; 1.5; 2)"), ExpValue::Number(1.5));
+        assert_eq!(calc("MAX(subtask.estimatePoint)"), ExpValue::Integer(8));
+        assert_eq!(
+            calc("MAX(subtask.estimatePoint;status=1)"),
+            ExpValue::Integer(5)
+        );
+        assert_eq!(
+            calc("MIN(dueDate; #2024-01-01#)"),
+            ExpValue::Date(1704067200000)
+        );
+        assert_eq!(calc("MIN(blank; 2)"), ExpValue::Integer(2));
+        assert_eq!(calc("MAX(subtask.missing)"), ExpValue::Null);
+        assert_eq!(
+            calc("MAX(title; 2)"),
+            ExpValue::Error(ValueError::TypeMismatch)
+        );
+    }
+
+    #[test]
+    fn comparison_filters() {
+        assert_eq!(
+            calc("SUM(subtask.estimatePoint;status>=3)"),
+            ExpValue::Integer(13)
+        );
+        assert_eq!(calc("COUNT(subtask;status<>4)"), ExpValue::Integer(2));
+        assert_eq!(
+            calc("COUNT(subtask;status<4;estimatePoint>2)"),
+            ExpValue::Integer(1)
+        );
+    }
+
+    #[test]
+    fn total_ordering() {
+        let mut values = vec![
+            ExpValue::String("b".to_string()),
+            ExpValue::Integer(3),
+            ExpValue::Error(ValueError::NaN),
+            ExpValue::Null,
+            ExpValue::Number(f64::NAN),
+            ExpValue::Date(0),
+            ExpValue::Number(-1.5),
+            ExpValue::String("a".to_string()),
+            ExpValue::Bool(true),
+        ];
+        values.sort_by(ExpValue::total_cmp);
+
+        assert_eq!(values[0], ExpValue::Null);
+        assert_eq!(values[1], ExpValue::Bool(true));
+        assert_eq!(values[2], ExpValue::Number(-1.5));
+        assert_eq!(values[3], ExpValue::Integer(3));
+        assert!(matches!(values[4], ExpValue::Number(n) if n.is_nan()));
+        assert_eq!(values[5], ExpValue::String("a".to_string()));
+        assert_eq!(values[7], ExpValue::Date(0));
+        assert_eq!(values[8], ExpValue::Error(ValueError::NaN));
+        assert_eq!(
+            ExpValue::Integer(2).total_cmp(&ExpValue::Number(2.0)),
+            Ordering::Equal
+        );
     }
 }
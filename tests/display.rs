@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod display_tests {
+    use std::collections::BTreeMap;
+
+    use formula::{ExpValue, FormatOptions, Locale, ValueError};
+
+    #[test]
+    fn canonical_text() {
+        assert_eq!(ExpValue::Number(3.0).to_string(), "3");
+        assert_eq!(
+            ExpValue::Number(0.1 + 0.2).to_string(),
+            "0.30000000000000004"
+        );
+        assert_eq!(ExpValue::Integer(-42).to_string(), "-42");
+        assert_eq!(ExpValue::Bool(true).to_string(), "true");
+        assert_eq!(ExpValue::Null.to_string(), "null");
+        assert_eq!(ExpValue::String("Ann".to_string()).to_string(), "Ann");
+        assert_eq!(ExpValue::Date(1709251200000).to_string(), "2024-03-01");
+        assert_eq!(
+            ExpValue::Date(1709287200000).to_string(),
+            "2024-03-01T10:00:00Z"
+        );
+        assert_eq!(ExpValue::Duration(99_000_000).to_string(), "1d3h30m");
+        assert_eq!(ExpValue::Duration(-1500).to_string(), "-1s500ms");
+        assert_eq!(
+            ExpValue::Error(ValueError::DivByZero).to_string(),
+            "#DivByZero"
+        );
+    }
+
+    #[test]
+    fn nested_values() {
+        let mut fields = BTreeMap::new();
+        fields.insert("name".to_string(), ExpValue::String("A \"B\"".to_string()));
+        fields.insert(
+            "points".to_string(),
+            ExpValue::Array(vec![ExpValue::Number(1.0), ExpValue::Number(2.5)]),
+        );
+        assert_eq!(
+            ExpValue::Object(fields).to_string(),
+            r#"{name: "A \"B\"", points: [1, 2.5]}"#
+        );
+        assert_eq!(
+            ExpValue::Array(vec![ExpValue::String("a".to_string()), ExpValue::Null]).to_string(),
+            r#"["a", null]"#
+        );
+    }
+
+    #[test]
+    fn locale_text() {
+        let en = FormatOptions::default();
+        let zh = FormatOptions {
+            locale: Locale::ZhCn,
+            ..Default::default()
+        };
+        let fixed = FormatOptions {
+            precision: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            ExpValue::Integer(1234567).to_locale_string(&en),
+            "1,234,567"
+        );
+        assert_eq!(ExpValue::Number(-9876.5).to_locale_string(&en), "-9,876.5");
+        assert_eq!(
+            ExpValue::Number(1234.567).to_locale_string(&fixed),
+            "1,234.57"
+        );
+        assert_eq!(ExpValue::Integer(3).to_locale_string(&fixed), "3.00");
+        assert_eq!(ExpValue::Bool(true).to_locale_string(&zh), "是");
+        assert_eq!(ExpValue::Null.to_locale_string(&en), "");
+        assert_eq!(
+            ExpValue::Date(1709251200000).to_locale_string(&zh),
+            "2024年3月1日"
+        );
+        assert_eq!(
+            ExpValue::Duration(99_000_000).to_locale_string(&zh),
+            "1天3小时30分钟"
+        );
+        assert_eq!(
+            ExpValue::Array(vec![ExpValue::Integer(1000), ExpValue::Integer(2)])
+                .to_locale_string(&zh),
+            "1,000、2"
+        );
+    }
+}
@@ -0,0 +1,191 @@
+#[cfg(test)]
+mod field_format_tests {
+    use formula::{
+        format_value, ExpValue, FieldType, FormatOptions, Locale, RoundingMode, ValueError,
+        MILLIS_PER_DAY, MILLIS_PER_HOUR,
+    };
+
+    fn display(value: ExpValue, field_type: FieldType, options: &FormatOptions) -> String {
+        format_value(&value, field_type, options).unwrap().display
+    }
+
+    #[test]
+    fn field_type_from_name() {
+        assert_eq!(
+            FieldType::from_name("percentage_number"),
+            Some(FieldType::PercentageNumber)
+        );
+        assert_eq!(FieldType::from_name("datetime"), Some(FieldType::DateTime));
+        assert_eq!(FieldType::from_name("text"), None);
+    }
+
+    #[test]
+    fn format_percentage() {
+        let result = format_value(
+            &ExpValue::Number(0.4567),
+            FieldType::PercentageNumber,
+            &FormatOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result.display, "45.67%");
+        assert_eq!(result.value, ExpValue::Number(0.4567));
+
+        let result = format_value(
+            &ExpValue::Number(1.25),
+            FieldType::PercentageBar,
+            &FormatOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result.display, "100.00%");
+        assert_eq!(result.value, ExpValue::Number(1.0));
+    }
+
+    #[test]
+    fn format_numbers() {
+        let options = FormatOptions::default();
+        assert_eq!(
+            display(ExpValue::Number(2.5), FieldType::Integer, &options),
+            "3"
+        );
+        assert_eq!(
+            display(ExpValue::Number(1.005), FieldType::Decimal, &options),
+            "1.01"
+        );
+        assert_eq!(
+            display(ExpValue::Number(-0.001), FieldType::Decimal, &options),
+            "0.00"
+        );
+
+        let options = FormatOptions {
+            precision: Some(1),
+            rounding: RoundingMode::HalfEven,
+            ..Default::default()
+        };
+        assert_eq!(
+            display(ExpValue::Number(0.25), FieldType::Decimal, &options),
+            "0.2"
+        );
+        assert_eq!(
+            display(ExpValue::Number(0.35), FieldType::Decimal, &options),
+            "0.4"
+        );
+    }
+
+    #[test]
+    fn round_large_scales() {
+        for mode in [
+            RoundingMode::HalfUp,
+            RoundingMode::HalfEven,
+            RoundingMode::Ceiling,
+        ] {
+            assert_eq!(mode.round(1e300, 10), 1e300);
+            assert_eq!(mode.round(1.5, 400), 1.5);
+            assert_eq!(mode.round(0.125, u32::MAX), 0.125);
+            assert_eq!(mode.round(-4e15, 2), -4e15);
+        }
+        assert_eq!(RoundingMode::HalfUp.round(0.125, 2), 0.13);
+    }
+
+    #[test]
+    fn round_below_integer() {
+        assert_eq!(RoundingMode::Floor.round(2.9999996, 0), 2.0);
+        assert_eq!(RoundingMode::Down.round(0.9999999, 0), 0.0);
+        assert_eq!(RoundingMode::Down.round(-0.9999999, 0), -0.0);
+        assert_eq!(RoundingMode::Ceiling.round(-2.9999996, 0), -2.0);
+        assert_eq!(RoundingMode::HalfUp.round(2.4999996, 0), 2.0);
+        assert_eq!(RoundingMode::HalfEven.round(2.4999996, 0), 2.0);
+        assert_eq!(RoundingMode::HalfEven.round(2.5000001, 0), 3.0);
+        assert_eq!(RoundingMode::Up.round(2.0000001, 0), 3.0);
+        assert_eq!(RoundingMode::Floor.round(1.2999999, 1), 1.2);
+        assert_eq!(RoundingMode::HalfUp.round(1.005, 2), 1.01);
+        assert_eq!(RoundingMode::Ceiling.round(0.001, 0), 1.0);
+        assert_eq!(RoundingMode::Floor.round(-0.001, 1), -0.1);
+
+        let options = FormatOptions {
+            rounding: RoundingMode::Down,
+            ..Default::default()
+        };
+        let result =
+            format_value(&ExpValue::Number(0.9999999), FieldType::Integer, &options).unwrap();
+        assert_eq!(result.display, "0");
+        assert_eq!(result.value, ExpValue::Integer(0));
+
+        let options = FormatOptions {
+            precision: Some(320),
+            ..Default::default()
+        };
+        let result = format_value(&ExpValue::Number(1e10), FieldType::Decimal, &options).unwrap();
+        assert_eq!(result.value, ExpValue::Number(1e10));
+    }
+
+    #[test]
+    fn format_dates() {
+        // 2024-03-01T10:30:00Z
+        let date = ExpValue::Date(1709251200000 + 10 * MILLIS_PER_HOUR + 1800000);
+        let zh = FormatOptions {
+            locale: Locale::ZhCn,
+            ..Default::default()
+        };
+
+        let result = format_value(&date, FieldType::Date, &FormatOptions::default()).unwrap();
+        assert_eq!(result.display, "2024-03-01");
+        assert_eq!(result.value, ExpValue::Date(1709251200000));
+        assert_eq!(
+            display(date.clone(), FieldType::DateTime, &zh),
+            "2024年3月1日 10:30"
+        );
+
+        let shifted = FormatOptions {
+            utc_offset_minutes: 14 * 60,
+            ..Default::default()
+        };
+        assert_eq!(display(date, FieldType::Date, &shifted), "2024-03-02");
+    }
+
+    #[test]
+    fn format_date_overflow() {
+        let shifted = FormatOptions {
+            utc_offset_minutes: 60,
+            ..Default::default()
+        };
+        for field_type in [FieldType::Date, FieldType::DateTime] {
+            let result = format_value(&ExpValue::Date(i64::MAX), field_type, &shifted).unwrap();
+            assert_eq!(result.value, ExpValue::Error(ValueError::Overflow));
+        }
+        assert_eq!(
+            ExpValue::Date(i64::MAX).to_locale_string(&shifted),
+            ExpValue::Error(ValueError::Overflow).to_string()
+        );
+    }
+
+    #[test]
+    fn format_durations() {
+        let duration = ExpValue::Duration(3 * MILLIS_PER_DAY + 4 * MILLIS_PER_HOUR);
+        let zh = FormatOptions {
+            locale: Locale::ZhCn,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            display(
+                duration.clone(),
+                FieldType::DateTime,
+                &FormatOptions::default()
+            ),
+            "3d 4h"
+        );
+        assert_eq!(
+            display(duration.clone(), FieldType::DateTime, &zh),
+            "3天4小时"
+        );
+        assert_eq!(display(duration, FieldType::Date, &zh), "3天");
+    }
+
+    #[test]
+    fn format_incompatible() {
+        let options = FormatOptions::default();
+        assert!(format_value(&ExpValue::Null, FieldType::Integer, &options).is_none());
+        assert!(format_value(&ExpValue::Number(1.0), FieldType::Date, &options).is_none());
+        assert!(format_value(&ExpValue::Number(f64::NAN), FieldType::Decimal, &options).is_none());
+    }
+}
@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod format_tests {
+    use formula::{format, Ast};
+
+    #[test]
+    fn normalizes_spelling() {
+        assert_eq!(format("a plus b times 2").unwrap(), "a + b * 2");
+        assert_eq!(format("a divide by b  mod 3").unwrap(), "a / b % 3");
+        assert_eq!(format("a   div b<>c").unwrap(), "a // b != c");
+        assert_eq!(format("a==b").unwrap(), "a = b");
+        assert_eq!(
+            format("sum(subtask.points;status=4)").unwrap(),
+            "SUM(subtask.points; status = 4)"
+        );
+        assert_eq!(format("coalesce(a;0;)").unwrap(), "COALESCE(a; 0)");
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(format("3.0 + 2 + 1.50").unwrap(), "3.0 + 2 + 1.5");
+        assert_eq!(
+            format("#2024-03-01# - 2024-03-01T10:00:00+02:00").unwrap(),
+            "#2024-03-01# - #2024-03-01T08:00:00Z#"
+        );
+        assert_eq!(format("due + 1w2d").unwrap(), "due + 9d");
+        assert_eq!(format("#2023-02-29#").unwrap(), "#2023-02-29#");
+        assert_eq!(format("[1,2 ,3,]").unwrap(), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_eq!(format("(a + b) * c").unwrap(), "(a + b) * c");
+        assert_eq!(format("a + (b * c)").unwrap(), "a + b * c");
+        assert_eq!(format("(a - b) - c").unwrap(), "a - b - c");
+        assert_eq!(format("a - (b - c)").unwrap(), "a - (b - c)");
+        assert_eq!(format("a ^ (b ^ c)").unwrap(), "a ^ b ^ c");
+        assert_eq!(format("(a ^ b) ^ c").unwrap(), "(a ^ b) ^ c");
+        assert_eq!(format("((a))").unwrap(), "a");
+        assert_eq!(format("(a + b)[0]").unwrap(), "(a + b)[0]");
+        assert_eq!(format("(1).x").unwrap(), "(1).x");
+        assert_eq!(format("SUM((a); 1)").unwrap(), "SUM((a); 1)");
+    }
+
+    #[test]
+    fn round_trip() {
+        let formulas = [
+            "a plus b times 2",
+            "(a + b) * (c - d) / e",
+            "a - (b - c) - (d + e)",
+            "2 ^ 3 ^ 2 + (2 ^ 3) ^ 2",
+            "1 << 2 + 1 >> (a mod 3)",
+            "-2 ^ 2 - -3",
+            "a + b > c * 2 = (d <= e)",
+            "1e20 + 2.5e-7 + 99999999999999999999",
+            "1e400 * a - -1e400 + 2E+999",
+            "#2024-03-01# + 1d3h30m - 2024-03-01T10:00:00.250Z",
+            "[a, b + 1, [c]][1][0]",
+            "parent.assignee?.name",
+            "(1).x + (a + b).c",
+            "SUM(subtask.points;status>=2) / COUNT(subtask)",
+            "COALESCE(a.b; (c); d + 1; 0)",
+            "MAX(1; a * 2; [b, c])",
+        ];
+        for formula in formulas {
+            let formatted = format(formula).unwrap();
+            assert_eq!(
+                Ast::parse(&formatted).unwrap().without_spans(),
+                Ast::parse(formula).unwrap().without_spans(),
+                "{} formatted as {}",
+                formula,
+                formatted
+            );
+            assert_eq!(format(&formatted).unwrap(), formatted);
+        }
+    }
+
+    #[test]
+    fn to_source() {
+        let ast = Ast::parse("a times (b plus 1)").unwrap();
+        assert_eq!(ast.to_source(), "a * (b + 1)");
+        assert!(format("a +").is_err());
+
+        assert_eq!(format("1e400+1").unwrap(), "1e400 + 1");
+        let ast = Ast::parse("1e400 - a").unwrap();
+        assert_eq!(ast.to_source(), "1e999 - a");
+        assert_eq!(
+            Ast::parse(&ast.to_source()).unwrap().without_spans(),
+            ast.without_spans()
+        );
+    }
+}
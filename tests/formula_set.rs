@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod formula_set_tests {
+    use std::collections::HashMap;
+
+    use formula::{Ast, CycleError, ExpValue, FormulaSet, Locale};
+
+    fn create_set() -> FormulaSet {
+        FormulaSet::new()
+            .formula(
+                "customfield_2018041",
+                Ast::parse("customfield_2018036 * 2").unwrap(),
+            )
+            .formula(
+                "customfield_2018036",
+                Ast::parse("SUM(subtask.estimatePoint;status=4) + base").unwrap(),
+            )
+            .formula(
+                "total",
+                Ast::parse("customfield_2018041 + customfield_2018036").unwrap(),
+            )
+    }
+
+    fn create_table() -> HashMap<String, ExpValue> {
+        let mut table = HashMap::new();
+        table.insert("base".to_string(), ExpValue::Integer(1));
+        table.insert(
+            "subtask".to_string(),
+            ExpValue::from(serde_json::json!([
+                {"estimatePoint": 3, "status": 4},
+                {"estimatePoint": 5, "status": 1},
+            ])),
+        );
+        table
+    }
+
+    #[test]
+    fn graph() {
+        let set = create_set();
+        let graph = set.graph();
+        assert_eq!(graph["customfield_2018041"], vec!["customfield_2018036"]);
+        assert!(graph["customfield_2018036"].is_empty());
+        assert_eq!(
+            graph["total"],
+            vec!["customfield_2018041", "customfield_2018036"]
+        );
+        assert_eq!(
+            set.evaluation_order().unwrap(),
+            vec!["customfield_2018036", "customfield_2018041", "total"]
+        );
+    }
+
+    #[test]
+    fn eval() {
+        let values = create_set().eval(&create_table()).unwrap();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values["customfield_2018036"], ExpValue::Integer(4));
+        assert_eq!(values["customfield_2018041"], ExpValue::Integer(8));
+        assert_eq!(values["total"], ExpValue::Integer(12));
+    }
+
+    #[test]
+    fn insert_replaces() {
+        let mut set = create_set();
+        set.insert("customfield_2018036", Ast::parse("base").unwrap());
+        assert_eq!(
+            set.names(),
+            vec!["customfield_2018041", "customfield_2018036", "total"]
+        );
+        assert_eq!(
+            set.eval(&create_table()).unwrap()["total"],
+            ExpValue::Integer(3)
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        let mut set = FormulaSet::new()
+            .formula("A", Ast::parse("base + 1").unwrap())
+            .formula("B", Ast::parse("A * 2").unwrap());
+        let error = set
+            .try_insert("A", Ast::parse("B + 1").unwrap())
+            .unwrap_err();
+        assert_eq!(error.path, vec!["A", "B", "A"]);
+        assert_eq!(
+            error.to_string(),
+            "formulas reference each other: A → B → A"
+        );
+        assert_eq!(error.message(Locale::ZhCn), "公式循环引用：A → B → A");
+        assert_eq!(error.code(), "F001");
+        assert_eq!(set.get("A"), Some(&Ast::parse("base + 1").unwrap()));
+        assert!(set.try_insert("C", Ast::parse("A + B").unwrap()).is_ok());
+
+        let error = set
+            .try_insert("D", Ast::parse("D + 1").unwrap())
+            .unwrap_err();
+        assert_eq!(error.path, vec!["D", "D"]);
+
+        set.insert("A", Ast::parse("C").unwrap());
+        let cycle = CycleError {
+            path: vec!["A".to_string(), "C".to_string(), "A".to_string()],
+        };
+        assert_eq!(set.evaluation_order(), Err(cycle.clone()));
+        assert_eq!(set.eval(&create_table()), Err(cycle));
+    }
+
+    #[test]
+    fn long_chain() {
+        let mut set = FormulaSet::new().formula("f0", Ast::parse("base").unwrap());
+        for i in 1..100_000 {
+            let ast = Ast::parse(&format!("f{} + 1", i - 1)).unwrap();
+            set.insert(&format!("f{}", i), ast);
+        }
+        let values = set.eval(&create_table()).unwrap();
+        assert_eq!(values["f99999"], ExpValue::Integer(100_000));
+
+        let error = set
+            .try_insert("f0", Ast::parse("f99999").unwrap())
+            .unwrap_err();
+        assert_eq!(error.path.len(), 100_001);
+        assert_eq!(error.path[..2], ["f0", "f99999"]);
+        assert!(set
+            .try_insert("g", Ast::parse("f99999 + f0").unwrap())
+            .is_ok());
+    }
+}
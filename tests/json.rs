@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod json_tests {
+    use formula::{table_from_json, EvalOptions, ExpValue};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn json_round_trip() {
+        let issue = json!({
+            "id": 9007199254740993i64,
+            "ratio": 0.25,
+            "whole": 2.0,
+            "title": "Login page",
+            "done": false,
+            "parent": null,
+            "subtask": [{"estimatePoint": 3}, {"estimatePoint": 5.5}],
+        });
+
+        let value = ExpValue::from(issue.clone());
+        match &value {
+            ExpValue::Object(fields) => {
+                assert_eq!(fields["id"], ExpValue::Integer(9007199254740993));
+                assert!(matches!(fields["whole"], ExpValue::Number(_)));
+                assert_eq!(fields["parent"], ExpValue::Null);
+            }
+            other => panic!("expected an object, got {:?}", other),
+        }
+        assert_eq!(Value::from(value), issue);
+    }
+
+    #[test]
+    fn values_without_json_form() {
+        assert_eq!(
+            Value::from(ExpValue::Date(86_400_000 + 1_500)),
+            json!("1970-01-02T00:00:01.500Z")
+        );
+        assert_eq!(Value::from(ExpValue::Duration(60_000)), json!(60000));
+        assert_eq!(Value::from(ExpValue::Number(f64::NAN)), Value::Null);
+        assert_eq!(
+            Value::from(ExpValue::Error(formula::ValueError::DivByZero)),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn mapped_round_trip() {
+        let millis = 1709251200000 + 1_500;
+        let date = Value::from(ExpValue::Date(millis));
+        assert_eq!(
+            formula::parse_date_literal(date.as_str().unwrap()),
+            Some(millis)
+        );
+        let duration = Value::from(ExpValue::Duration(-60_000));
+        assert_eq!(ExpValue::from(duration), ExpValue::Integer(-60_000));
+
+        for value in [
+            json!(i64::MIN),
+            json!(-0.5),
+            json!(1e300),
+            json!({"a": [1, "b", null, true]}),
+        ] {
+            assert_eq!(Value::from(ExpValue::from(value.clone())), value);
+        }
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn exact_decimals() {
+        use formula::Decimal;
+        use std::str::FromStr;
+
+        let decimal = Decimal::from_str("0.1000000000000000000000000001").unwrap();
+        assert_eq!(
+            Value::from(ExpValue::Decimal(decimal)),
+            json!("0.1000000000000000000000000001")
+        );
+        assert_eq!(
+            Value::from(ExpValue::Decimal(Decimal::from_str("0.30").unwrap())),
+            json!("0.30")
+        );
+        assert_eq!(
+            ExpValue::from(json!(u64::MAX)),
+            ExpValue::Decimal(Decimal::from(u64::MAX))
+        );
+        assert_eq!(
+            Value::from(ExpValue::from(json!(u64::MAX))),
+            json!(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn evaluate_issue_json() {
+        let table = table_from_json(json!({
+            "subtask": [
+                {"estimatePoint": 3, "status": 4},
+                {"estimatePoint": 5, "status": 1},
+            ],
+        }));
+        let result = formula::eval_with(
+            formula::parse("SUM(subtask.estimatePoint;status=4) + subtask[1].estimatePoint")
+                .unwrap(),
+            &table,
+            &EvalOptions::default(),
+        );
+        assert_eq!(Value::from(result), json!(8));
+    }
+}
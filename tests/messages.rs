@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod messages_tests {
+    use std::collections::HashMap;
+
+    use formula::{
+        Ast, BinaryOperator, DiagnosticKind, FieldType, Locale, Schema, TypeErrorKind, ValueError,
+        ValueType,
+    };
+
+    #[test]
+    fn parse_errors() {
+        let err = Ast::parse("COUNT(relationship;issueTypeId=)").unwrap_err();
+        assert_eq!(err.code(), "P001");
+        assert_eq!(err.message(Locale::En), err.to_string());
+        assert_eq!(
+            err.message(Locale::ZhCn),
+            "第 1 行第 32 列应为字段名、数字、日期、时长或列表，实际为 `)`"
+        );
+
+        let err = Ast::parse("(关联总个数").unwrap_err();
+        assert_eq!(err.code(), "P002");
+        assert_eq!((err.offset, err.char_offset, err.column), (16, 6, 7));
+        assert!(err
+            .message(Locale::ZhCn)
+            .starts_with("第 1 行第 7 列应为`)`"));
+
+        let err = Ast::parse("a +").unwrap_err();
+        assert_eq!(err.code(), "P002");
+        assert!(err.message(Locale::ZhCn).ends_with("但公式已结束"));
+    }
+
+    #[test]
+    fn type_errors() {
+        let mut types = HashMap::new();
+        types.insert("title".to_string(), ValueType::String);
+        types.insert("dueDate".to_string(), ValueType::Date);
+        let err = Ast::parse("title * 2")
+            .unwrap()
+            .infer_type(&types)
+            .unwrap_err();
+        assert_eq!(err.code(), "T001");
+        assert_eq!(
+            err.message(Locale::En),
+            "cannot apply `*` to String and Number"
+        );
+        assert_eq!(err.message(Locale::ZhCn), "无法对文本和数字使用 `*`");
+
+        let err = Ast::parse("dueDate")
+            .unwrap()
+            .check_field_type(FieldType::Integer, &types)
+            .unwrap_err();
+        assert_eq!(err.code(), "T002");
+        assert_eq!(
+            err.message(Locale::ZhCn),
+            "公式结果为日期，但字段类型是整数"
+        );
+        assert_eq!(
+            TypeErrorKind::InvalidOperands(
+                BinaryOperator::Add,
+                ValueType::Array(Box::new(ValueType::Date)),
+                ValueType::Bool
+            )
+            .message(Locale::ZhCn),
+            "无法对日期列表和布尔值使用 `+`"
+        );
+    }
+
+    #[test]
+    fn diagnostics() {
+        let schema = Schema::new().field("estimatePoint", ValueType::Number);
+        let diagnostics = schema.validate(&Ast::parse("estimatePoint + lastUpdated").unwrap());
+        assert_eq!(diagnostics[0].code(), "S001");
+        assert_eq!(
+            diagnostics[0].message(Locale::ZhCn),
+            "未知字段 `lastUpdated`"
+        );
+
+        let unknown = DiagnosticKind::UnknownIdentifier("关联总个数".to_string());
+        assert_eq!(unknown.code(), "S001");
+        assert_eq!(unknown.message(Locale::En), "unknown field `关联总个数`");
+        assert_eq!(unknown.message(Locale::ZhCn), "未知字段 `关联总个数`");
+        assert_eq!(
+            DiagnosticKind::NotACollection("dueDate".to_string(), ValueType::Date)
+                .message(Locale::ZhCn),
+            "`dueDate` 是日期，不是集合"
+        );
+    }
+
+    #[test]
+    fn value_errors() {
+        assert_eq!(ValueError::DivByZero.code(), "V004");
+        assert_eq!(
+            ValueError::DivByZero.message(Locale::En),
+            "division by zero"
+        );
+        assert_eq!(ValueError::DivByZero.message(Locale::ZhCn), "除数为零");
+        assert_eq!(ValueError::NullMember.code(), "V010");
+    }
+}
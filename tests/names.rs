@@ -0,0 +1,240 @@
+#[cfg(test)]
+mod names_tests {
+    use std::collections::HashMap;
+
+    use formula::{format, Ast, AstKind, CompareOperator, ExpValue, FunctionPart};
+
+    fn names() -> HashMap<String, String> {
+        let mut names = HashMap::new();
+        names.insert("故事价值".to_string(), "customfield_21859018".to_string());
+        names.insert(
+            "Story Points".to_string(),
+            "customfield_21859018".to_string(),
+        );
+        names.insert("关联总个数".to_string(), "relationCount".to_string());
+        names.insert("子任务".to_string(), "subtask".to_string());
+        names.insert("状态".to_string(), "status".to_string());
+        names
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let ast = Ast::parse("关联总个数 + 多久没更新").unwrap();
+        match ast.kind {
+            AstKind::Binary(_, lhs, rhs) => {
+                assert_eq!(lhs.kind, AstKind::Identifier("关联总个数".to_string()));
+                assert_eq!(rhs.kind, AstKind::Identifier("多久没更新".to_string()));
+            }
+            _ => panic!("not binary"),
+        }
+        assert!(Ast::parse("3d天").is_err());
+    }
+
+    #[test]
+    fn references() {
+        let ast = Ast::parse("{Story Points} * 2").unwrap();
+        match ast.kind {
+            AstKind::Binary(_, lhs, _) => {
+                assert_eq!(lhs.kind, AstKind::Identifier("Story Points".to_string()));
+                assert_eq!((lhs.span.start, lhs.span.end), (0, 14));
+            }
+            _ => panic!("not binary"),
+        }
+        assert_eq!(
+            Ast::parse("{ 故事价值 }").unwrap().kind,
+            AstKind::Identifier("故事价值".to_string())
+        );
+        assert!(Ast::parse("{}").is_err());
+        assert!(Ast::parse("{a\nb}").is_err());
+    }
+
+    #[test]
+    fn resolve_and_eval() {
+        let mut table = HashMap::new();
+        table.insert("customfield_21859018".to_string(), ExpValue::Integer(5));
+        table.insert("relationCount".to_string(), ExpValue::Integer(2));
+
+        let ast = Ast::parse("{故事价值} * 2 + 关联总个数").unwrap();
+        assert_eq!(
+            ast.eval(&table),
+            ExpValue::Error(formula::ValueError::UnknownIdentifier)
+        );
+        let resolved = ast.resolve_names(&names());
+        assert_eq!(resolved.eval(&table), ExpValue::Integer(12));
+        assert_eq!(resolved.span, ast.span);
+        assert_eq!(
+            resolved.to_source(),
+            "customfield_21859018 * 2 + relationCount"
+        );
+    }
+
+    #[test]
+    fn function_references() {
+        let ast = Ast::parse("SUM({子任务}.{Story Points}; {状态}={已完成})")
+            .unwrap()
+            .resolve_names(&names());
+        match ast.kind {
+            AstKind::Function(func) => assert_eq!(
+                func.parts,
+                vec![
+                    FunctionPart::IdentifierFWithField(
+                        "subtask".to_string(),
+                        vec!["customfield_21859018".to_string()]
+                    ),
+                    FunctionPart::CompareExpression(
+                        vec!["status".to_string()],
+                        CompareOperator::Equal,
+                        "已完成".to_string()
+                    ),
+                ]
+            ),
+            _ => panic!("not function"),
+        }
+    }
+
+    #[test]
+    fn dotted_display_names() {
+        let mut names = HashMap::new();
+        names.insert(
+            "Story.Points".to_string(),
+            "customfield_21859018".to_string(),
+        );
+        let ast = Ast::parse("SUM(subtask.{Story.Points}; {Story.Points}>2)")
+            .unwrap()
+            .resolve_names(&names);
+        assert_eq!(
+            ast.to_source(),
+            "SUM(subtask.customfield_21859018; customfield_21859018 > 2)"
+        );
+    }
+
+    #[test]
+    fn format_references() {
+        assert_eq!(
+            format("{Story Points}+{故事价值}*parent.{due date}").unwrap(),
+            "{Story Points} + 故事价值 * parent.{due date}"
+        );
+        assert_eq!(
+            format("sum({子任务}.{Story Points};{状态}={In Progress})").unwrap(),
+            "SUM(子任务.{Story Points}; 状态 = {In Progress})"
+        );
+        let formula = "{a b} - {1x}[0] + COUNT({子任务}; {x.y}>2)";
+        assert_eq!(
+            Ast::parse(&format(formula).unwrap())
+                .unwrap()
+                .without_spans(),
+            Ast::parse(formula).unwrap().without_spans()
+        );
+    }
+}
+
+#[cfg(test)]
+mod rename_tests {
+    use std::collections::HashMap;
+
+    use formula::{ids_to_names, names_to_ids, rename_fields, Ast, Locale, RenameError, Span};
+
+    fn names() -> HashMap<String, String> {
+        let mut names = HashMap::new();
+        names.insert("customfield_19880148".to_string(), "故事点".to_string());
+        names.insert("customfield_1".to_string(), "Due Date".to_string());
+        names.insert("subtask".to_string(), "子任务".to_string());
+        names.insert("status".to_string(), "状态".to_string());
+        names
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(
+            ids_to_names("customfield_19880148*3", &names()).unwrap(),
+            "{故事点}*3"
+        );
+        assert_eq!(
+            names_to_ids("{故事点}*3", &names()).unwrap(),
+            "customfield_19880148*3"
+        );
+        assert_eq!(
+            names_to_ids("故事点 *3", &names()).unwrap(),
+            "customfield_19880148 *3"
+        );
+
+        let stored =
+            "SUM(subtask.customfield_19880148;status=DONE)  +  (parent).customfield_1 - unknown";
+        let shown = ids_to_names(stored, &names()).unwrap();
+        assert_eq!(
+            shown,
+            "SUM({子任务}.{故事点};{状态}=DONE)  +  (parent).{Due Date} - unknown"
+        );
+        assert_eq!(names_to_ids(&shown, &names()).unwrap(), stored);
+    }
+
+    #[test]
+    fn references() {
+        let source = "a?.{b.c} + COUNT(x.y; z>2) * [d][0]";
+        let references = Ast::parse(source).unwrap().field_references(source);
+        assert_eq!(
+            references
+                .iter()
+                .map(|r| (r.name.as_str(), &source[r.span.start..r.span.end]))
+                .collect::<Vec<_>>(),
+            vec![
+                ("a", "a"),
+                ("b.c", "{b.c}"),
+                ("x", "x"),
+                ("y", "y"),
+                ("z", "z"),
+                ("d", "d"),
+            ]
+        );
+        assert_eq!(references[1].span, Span { start: 3, end: 8 });
+    }
+
+    #[test]
+    fn rename() {
+        let mut names = HashMap::new();
+        names.insert("a".to_string(), "Story Points".to_string());
+        names.insert("b".to_string(), "points".to_string());
+        assert_eq!(
+            rename_fields("a+b", &names).unwrap(),
+            "{Story Points}+points"
+        );
+        assert!(matches!(
+            rename_fields("a +", &names),
+            Err(RenameError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_names() {
+        for name in ["x}y", "a\nb", ""] {
+            let mut names = HashMap::new();
+            names.insert("x".to_string(), name.to_string());
+            assert_eq!(
+                ids_to_names("x + 1", &names),
+                Err(RenameError::InvalidName(name.to_string()))
+            );
+            assert_eq!(
+                rename_fields("x + 1", &names),
+                Err(RenameError::InvalidName(name.to_string()))
+            );
+        }
+        let mut names = HashMap::new();
+        names.insert("x".to_string(), "x}y".to_string());
+        // names that are not used do not matter
+        assert_eq!(ids_to_names("z + 1", &names).unwrap(), "z + 1");
+        let error = ids_to_names("x", &names).unwrap_err();
+        assert_eq!(error.code(), "N001");
+        assert_eq!(error.to_string(), "`x}y` cannot be written as a field name");
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let mut names = names();
+        names.insert("customfield_2".to_string(), "故事点".to_string());
+        let error = names_to_ids("{故事点}*3", &names).unwrap_err();
+        assert_eq!(error, RenameError::DuplicateName("故事点".to_string()));
+        assert_eq!(error.code(), "N002");
+        assert_eq!(error.message(Locale::ZhCn), "多个字段同名 `故事点`");
+        assert!(ids_to_names("customfield_2", &names).is_ok());
+    }
+}
@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod optimize_tests {
+    use std::collections::HashMap;
+
+    use formula::{Ast, EvalOptions, ExpValue, NullMode, ValueType};
+
+    fn types() -> HashMap<String, ValueType> {
+        let mut types = HashMap::new();
+        types.insert("estimatePoint".to_string(), ValueType::Number);
+        types.insert("a".to_string(), ValueType::Number);
+        types.insert("b".to_string(), ValueType::Number);
+        types.insert("title".to_string(), ValueType::String);
+        types
+    }
+
+    fn optimize(formula: &str) -> String {
+        Ast::parse(formula).unwrap().optimize(&types()).to_source()
+    }
+
+    #[test]
+    fn simplify() {
+        assert_eq!(optimize("estimatePoint*3*2"), "estimatePoint * 6");
+        assert_eq!(optimize("2 * (estimatePoint * 4)"), "estimatePoint * 8");
+        assert_eq!(optimize("(a + 0) * 1"), "a");
+        assert_eq!(optimize("0 + a * 1 - 0"), "a");
+        assert_eq!(optimize("a - a"), "a * 0");
+        assert_eq!(optimize("1 + 2 * 3 + a"), "7 + a");
+        assert_eq!(optimize("[1, 2][0] + MAX(1; 4)"), "5");
+        assert_eq!(optimize("a * 3 * 5"), "a * 3 * 5");
+    }
+
+    #[test]
+    fn kept() {
+        // unknown or non-numeric fields may be null, text or arrays
+        assert_eq!(optimize("unknown + 0"), "unknown + 0");
+        assert_eq!(optimize("title + 0"), "title + 0");
+        assert_eq!(optimize("unknown - unknown"), "unknown - unknown");
+        // results without literal syntax stay as written
+        assert_eq!(optimize("1 < 2"), "1 < 2");
+        assert_eq!(optimize("1 / 0"), "1 / 0");
+        // `/` always divides as floats and infinity times 0 is NaN
+        assert_eq!(optimize("a / 1"), "a / 1");
+        assert_eq!(optimize("a * 2 * 0"), "a * 2 * 0");
+
+        let options = EvalOptions {
+            null_mode: NullMode::Zero,
+            ..Default::default()
+        };
+        let ast = Ast::parse("a + 0")
+            .unwrap()
+            .optimize_with(&types(), &options);
+        assert_eq!(ast.to_source(), "a + 0");
+    }
+
+    #[test]
+    fn same_values() {
+        let formulas = [
+            "estimatePoint*3*2",
+            "(a + 0) * 1 - b / 1",
+            "a - a + (b - b)",
+            "a * 2 * 7 + 1 * 2 ^ 3",
+            "COALESCE(a; 1 + 1) * (4 * b)",
+            "a * 2 * 0 + b / 1",
+        ];
+        let tables = [
+            vec![
+                ("estimatePoint", ExpValue::Integer(5)),
+                ("a", ExpValue::Number(1.5)),
+            ],
+            vec![("a", ExpValue::Null), ("b", ExpValue::Integer(-3))],
+            vec![
+                ("a", ExpValue::Number(f64::INFINITY)),
+                ("b", ExpValue::Number(0.1)),
+            ],
+            vec![
+                ("a", ExpValue::Number(1e308)),
+                ("b", ExpValue::Integer(9007199254740993)),
+            ],
+        ];
+        for options in [
+            EvalOptions::default(),
+            EvalOptions {
+                null_mode: NullMode::Zero,
+                ..Default::default()
+            },
+        ] {
+            for table in &tables {
+                let mut table: HashMap<String, ExpValue> = table
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.clone()))
+                    .collect();
+                for name in ["estimatePoint", "a", "b"] {
+                    table
+                        .entry(name.to_string())
+                        .or_insert(ExpValue::Integer(2));
+                }
+                for formula in formulas {
+                    let ast = Ast::parse(formula).unwrap();
+                    let optimized = ast.optimize_with(&types(), &options);
+                    let (expected, actual) = (
+                        ast.eval_with(&table, &options),
+                        optimized.eval_with(&table, &options),
+                    );
+                    match expected {
+                        ExpValue::Number(n) if n.is_nan() => {
+                            assert!(matches!(actual, ExpValue::Number(m) if m.is_nan()))
+                        }
+                        // `Integer(3) == Number(3.0)`, so compare the variants too
+                        expected => assert_eq!(
+                            (std::mem::discriminant(&actual), actual),
+                            (std::mem::discriminant(&expected), expected),
+                            "{}",
+                            formula
+                        ),
+                    }
+                }
+            }
+        }
+    }
+}
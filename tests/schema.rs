@@ -0,0 +1,149 @@
+#[cfg(test)]
+mod schema_tests {
+    use formula::{Ast, DiagnosticKind, FieldType, Schema, Span, ValueType};
+
+    fn create_schema() -> Schema {
+        Schema::new()
+            .field("customfield_19880148", ValueType::Number)
+            .field(
+                "subtask",
+                ValueType::parse("Array<{estimatePoint: Number, status: Number, title: String}>")
+                    .unwrap(),
+            )
+            .field("dueDate", ValueType::Date)
+            .field(
+                "parent",
+                ValueType::parse("{assignee: {name: String}}").unwrap(),
+            )
+    }
+
+    fn validate(formula: &str) -> Vec<(DiagnosticKind, Span)> {
+        create_schema()
+            .validate(&Ast::parse(formula).unwrap())
+            .into_iter()
+            .map(|d| (d.kind, d.span))
+            .collect()
+    }
+
+    #[test]
+    fn parse_value_type() {
+        let value_type =
+            ValueType::parse("Array<{status: Number, estimatePoint: Number}>").unwrap();
+        assert_eq!(
+            value_type.name(),
+            "Array<{estimatePoint: Number, status: Number}>"
+        );
+        assert_eq!(
+            ValueType::parse(value_type.name().as_str()),
+            Some(value_type)
+        );
+        assert_eq!(ValueType::parse("Array<Number"), None);
+        assert_eq!(ValueType::parse("Money"), None);
+    }
+
+    #[test]
+    fn resolve_paths() {
+        let schema = create_schema();
+        assert_eq!(
+            schema.resolve("subtask.estimatePoint"),
+            Some(ValueType::Array(Box::new(ValueType::Number)))
+        );
+        assert_eq!(schema.resolve("GET_NOW"), Some(ValueType::Date));
+        assert_eq!(schema.resolve("subtask.assignee"), None);
+    }
+
+    #[test]
+    fn validate_valid_formulas() {
+        for formula in [
+            "customfield_19880148*3",
+            "SUM(subtask.estimatePoint;status=4)/SUM(subtask.estimatePoint;)",
+            "COUNT(subtask;)",
+            "GET_NOW-dueDate",
+            "parent.assignee?.name",
+            "SUM(subtask.estimatePoint * 2)",
+        ] {
+            assert_eq!(validate(formula), vec![], "{}", formula);
+        }
+    }
+
+    #[test]
+    fn validate_unknown_identifiers() {
+        assert_eq!(
+            validate("customfield_1 + SUM(subtask.points;status=4)"),
+            vec![
+                (
+                    DiagnosticKind::UnknownIdentifier("customfield_1".to_string()),
+                    Span { start: 0, end: 13 }
+                ),
+                (
+                    DiagnosticKind::UnknownField("subtask.points".to_string()),
+                    Span { start: 20, end: 34 }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_unknown_members() {
+        assert_eq!(
+            validate("parent.assignee.email + parent.owner.name"),
+            vec![
+                (
+                    DiagnosticKind::UnknownField("parent.assignee.email".to_string()),
+                    Span { start: 0, end: 21 }
+                ),
+                (
+                    DiagnosticKind::UnknownField("parent.owner".to_string()),
+                    Span { start: 24, end: 36 }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_incompatible_fields() {
+        assert_eq!(
+            validate("SUM(subtask.title;state=4) + COUNT(dueDate;)"),
+            vec![
+                (
+                    DiagnosticKind::IncompatibleField(
+                        "subtask.title".to_string(),
+                        ValueType::Array(Box::new(ValueType::Number)),
+                        ValueType::Array(Box::new(ValueType::String)),
+                    ),
+                    Span { start: 4, end: 17 }
+                ),
+                (
+                    DiagnosticKind::UnknownField("subtask.state".to_string()),
+                    Span { start: 18, end: 25 }
+                ),
+                (
+                    DiagnosticKind::NotACollection("dueDate".to_string(), ValueType::Date),
+                    Span { start: 35, end: 42 }
+                ),
+            ]
+        );
+
+        let diagnostics = validate("COUNT(subtask;status=DONE)");
+        assert_eq!(
+            diagnostics[0].0,
+            DiagnosticKind::InvalidFilterValue(
+                "subtask.status".to_string(),
+                ValueType::Number,
+                "DONE".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn check_against_field_type() {
+        let schema = create_schema();
+        let diagnostics = schema.check(&Ast::parse("GET_NOW-dueDate").unwrap(), FieldType::Integer);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "formula produces Duration but the field is integer"
+        );
+    }
+}
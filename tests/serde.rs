@@ -0,0 +1,43 @@
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use formula::{Ast, ExpValue, Expression, Function};
+
+    #[test]
+    fn ast_round_trip() {
+        let ast =
+            Ast::parse("SUM(subtask.estimatePoint;status=4) / parent?.points[0] - 3d").unwrap();
+        let json = serde_json::to_string(&ast).unwrap();
+        assert_eq!(serde_json::from_str::<Ast>(&json).unwrap(), ast);
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let value = ExpValue::Array(vec![
+            ExpValue::Integer(1),
+            ExpValue::Number(0.5),
+            ExpValue::Date(86_400_000),
+            ExpValue::Null,
+            ExpValue::Error(formula::ValueError::DivByZero),
+        ]);
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(serde_json::from_str::<ExpValue>(&json).unwrap(), value);
+    }
+
+    #[test]
+    fn expression_round_trip() {
+        let expression =
+            Expression::from_pairs(formula::parse("COUNT(subtask;status=4)+points").unwrap())
+                .unwrap();
+        let json = serde_json::to_string(&expression).unwrap();
+        let decoded = serde_json::from_str::<Expression>(&json).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", expression));
+
+        let function = match &decoded.parts[0] {
+            formula::ExpressionPart::Function(function) => function.clone(),
+            other => panic!("expected a function, got {:?}", other),
+        };
+        let json = serde_json::to_value(&function).unwrap();
+        assert_eq!(json["name"], "COUNT");
+        assert_eq!(serde_json::from_value::<Function>(json).unwrap(), function);
+    }
+}
@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod type_inference_tests {
+    use formula::{Ast, FieldType, Span, TypeErrorKind, ValueType};
+    use std::collections::HashMap;
+
+    fn create_type_table() -> HashMap<String, ValueType> {
+        let mut types = HashMap::new();
+
+        types.insert("estimatePoint".to_string(), ValueType::Number);
+        types.insert("dueDate".to_string(), ValueType::Date);
+        types.insert("title".to_string(), ValueType::String);
+
+        types
+    }
+
+    fn infer(formula: &str) -> Result<ValueType, TypeErrorKind> {
+        Ast::parse(formula)
+            .unwrap()
+            .infer_type(&create_type_table())
+            .map_err(|e| e.kind)
+    }
+
+    #[test]
+    fn infer_types() {
+        assert_eq!(infer("estimatePoint*3"), Ok(ValueType::Number));
+        assert_eq!(infer("GET_NOW-GET_UPDATE_TIME"), Ok(ValueType::Duration));
+        assert_eq!(infer("dueDate - 3d"), Ok(ValueType::Date));
+        assert_eq!(infer("(GET_NOW - dueDate) / 1d"), Ok(ValueType::Number));
+        assert_eq!(
+            infer("SUM(subtask.estimatePoint;status=4)/SUM(subtask.estimatePoint;)"),
+            Ok(ValueType::Number)
+        );
+        assert_eq!(infer("customfield_2018036 + 1"), Ok(ValueType::Any));
+        assert_eq!(infer("dueDate < GET_NOW"), Ok(ValueType::Bool));
+        assert_eq!(infer("customfield_2018036 = title"), Ok(ValueType::Bool));
+        assert_eq!(infer("MAX(estimatePoint; 3)"), Ok(ValueType::Number));
+        assert!(infer("title > 3").is_err());
+    }
+
+    #[test]
+    fn infer_array_types() {
+        let mut types = create_type_table();
+        types.insert(
+            "subtask".to_string(),
+            ValueType::parse("Array<{estimatePoint: Number, title: String}>").unwrap(),
+        );
+        let infer = |formula: &str| {
+            Ast::parse(formula)
+                .unwrap()
+                .infer_type(&types)
+                .map_err(|e| e.kind)
+        };
+        let numbers = ValueType::Array(Box::new(ValueType::Number));
+
+        assert_eq!(infer("[1, 2] * 2"), Ok(numbers.clone()));
+        assert_eq!(infer("subtask.estimatePoint * 2"), Ok(numbers));
+        assert_eq!(infer("subtask.title[0]"), Ok(ValueType::String));
+        assert_eq!(
+            infer("SUM(subtask.estimatePoint * estimatePoint)"),
+            Ok(ValueType::Number)
+        );
+        assert_eq!(
+            infer("estimatePoint[0]"),
+            Err(TypeErrorKind::InvalidIndex(
+                ValueType::Number,
+                ValueType::Number
+            ))
+        );
+        assert!(infer("subtask.title * 2").is_err());
+    }
+
+    #[test]
+    fn infer_null_functions() {
+        assert_eq!(infer("COALESCE(missing; dueDate)"), Ok(ValueType::Date));
+        assert_eq!(infer("IFNULL(estimatePoint; 0) * 2"), Ok(ValueType::Number));
+        assert_eq!(infer("ISBLANK(dueDate)"), Ok(ValueType::Bool));
+        assert!(infer("COALESCE(title * 2; 0)").is_err());
+    }
+
+    #[test]
+    fn infer_invalid_operands() {
+        let err = Ast::parse("estimatePoint + (GET_NOW - dueDate) * title")
+            .unwrap()
+            .infer_type(&create_type_table())
+            .unwrap_err();
+
+        assert_eq!(
+            err.kind,
+            TypeErrorKind::InvalidOperands(
+                formula::BinaryOperator::Multiply,
+                ValueType::Duration,
+                ValueType::String
+            )
+        );
+        assert_eq!(err.span, Span { start: 17, end: 43 });
+    }
+
+    #[test]
+    fn check_field_type() {
+        let types = create_type_table();
+        let ast = Ast::parse("GET_NOW-GET_UPDATE_TIME").unwrap();
+
+        assert!(ast.check_field_type(FieldType::DateTime, &types).is_ok());
+        let err = ast
+            .check_field_type(FieldType::Integer, &types)
+            .unwrap_err();
+        assert_eq!(
+            err.kind,
+            TypeErrorKind::FieldTypeMismatch(FieldType::Integer, ValueType::Duration)
+        );
+        assert_eq!(
+            err.to_string(),
+            "formula produces Duration but the field is integer"
+        );
+    }
+}
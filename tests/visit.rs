@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod visit_tests {
+    use std::collections::HashMap;
+
+    use formula::{
+        fold_children, walk_function, walk_function_mut, Ast, AstKind, ExpValue, Fold, Function,
+        Span, Visitor, VisitorMut,
+    };
+
+    #[derive(Default)]
+    struct Cost {
+        nodes: usize,
+        functions: Vec<String>,
+        identifiers: Vec<String>,
+        members: Vec<String>,
+        filters: Vec<String>,
+    }
+
+    impl Visitor for Cost {
+        fn visit_ast(&mut self, ast: &Ast) {
+            self.nodes += 1;
+            formula::walk_ast(self, ast);
+        }
+
+        fn visit_identifier(&mut self, name: &str, _span: Span) {
+            self.identifiers.push(name.to_string());
+        }
+
+        fn visit_member(&mut self, name: &str, _span: Span) {
+            self.members.push(name.to_string());
+        }
+
+        fn visit_filter_field(&mut self, path: &[String], _span: Span) {
+            self.filters.push(path.join("."));
+        }
+
+        fn visit_function(&mut self, func: &Function, _span: Span) {
+            self.functions.push(func.name.clone());
+            walk_function(self, func);
+        }
+    }
+
+    #[test]
+    fn visitor() {
+        let ast = Ast::parse("a.b + COALESCE(c; MAX(1; d?.e)) * [2, 3][0]").unwrap();
+        let mut cost = Cost::default();
+        ast.visit(&mut cost);
+        assert_eq!(cost.functions, vec!["COALESCE", "MAX"]);
+        assert_eq!(cost.identifiers, vec!["a", "c", "d"]);
+        assert_eq!(cost.members, vec!["b", "e"]);
+        assert_eq!(cost.nodes, 14);
+
+        let mut cost = Cost::default();
+        Ast::parse("SUM(subtask.story.points; status=2)")
+            .unwrap()
+            .visit(&mut cost);
+        assert_eq!(cost.identifiers, vec!["subtask"]);
+        assert_eq!(cost.members, vec!["story", "points"]);
+        assert_eq!(cost.filters, vec!["status"]);
+
+        let names = ast
+            .get_dependencies()
+            .into_iter()
+            .map(|i| i.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a", "c", "d"]);
+    }
+
+    struct Upper;
+
+    impl VisitorMut for Upper {
+        fn visit_identifier_mut(&mut self, name: &mut String, _span: Span) {
+            *name = name.to_uppercase();
+        }
+
+        fn visit_function_mut(&mut self, func: &mut Function, _span: Span) {
+            func.name = func.name.to_lowercase();
+            walk_function_mut(self, func);
+        }
+    }
+
+    #[test]
+    fn visitor_mut() {
+        let mut ast = Ast::parse("a + SUM(b; c * 2)").unwrap();
+        ast.visit_mut(&mut Upper);
+        assert_eq!(ast.to_source(), "A + SUM(B; C * 2)");
+        match &ast.kind {
+            AstKind::Binary(_, _, rhs) => match &rhs.kind {
+                AstKind::Function(func) => assert_eq!(func.name, "sum"),
+                _ => panic!("not function"),
+            },
+            _ => panic!("not binary"),
+        }
+    }
+
+    struct Inline(HashMap<String, ExpValue>);
+
+    impl Fold for Inline {
+        fn fold_ast(&mut self, ast: Ast) -> Ast {
+            let ast = fold_children(self, ast);
+            match &ast.kind {
+                AstKind::Identifier(name) if self.0.contains_key(name) => Ast {
+                    kind: AstKind::Literal(self.0[name].clone()),
+                    span: ast.span,
+                },
+                _ => ast,
+            }
+        }
+    }
+
+    #[test]
+    fn fold() {
+        let mut constants = HashMap::new();
+        constants.insert("rate".to_string(), ExpValue::Integer(3));
+        let ast = Ast::parse("hours * rate + COALESCE(x; rate * 2)")
+            .unwrap()
+            .fold(&mut Inline(constants));
+        assert_eq!(ast.to_source(), "hours * 3 + COALESCE(x; 3 * 2)");
+        assert_eq!(ast.span, Span { start: 0, end: 36 });
+
+        let mut constants = HashMap::new();
+        constants.insert("rate".to_string(), ExpValue::Integer(3));
+        let ast = Ast::parse("COALESCE(rate; x) + MAX(rate.high; y)")
+            .unwrap()
+            .fold(&mut Inline(constants));
+        assert_eq!(ast.to_source(), "COALESCE(3; x) + MAX((3).high; y)");
+    }
+}